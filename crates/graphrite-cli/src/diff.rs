@@ -0,0 +1,108 @@
+/// Lines of unchanged context around each hunk.
+const CONTEXT: usize = 3;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Line-level edit script from `a` to `b` via a longest common subsequence; diagrams
+/// are small enough that the quadratic table is not a concern.
+fn ops(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(Op::Delete);
+            i += 1;
+        } else {
+            out.push(Op::Insert);
+            j += 1;
+        }
+    }
+    out
+}
+
+fn push_line(out: &mut String, mark: char, line: &str) {
+    out.push(mark);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Unified diff from `old` to `new`, labelled `path` on both sides; empty when equal.
+pub fn unified(path: &str, old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = ops(&a, &b);
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k] != Op::Equal).collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+    // group changes whose context would overlap into one hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &k in &changed {
+        match hunks.last_mut() {
+            Some(h) if k - h.1 <= 2 * CONTEXT => h.1 = k,
+            _ => hunks.push((k, k)),
+        }
+    }
+    let mut out = format!("--- {}\n+++ {}\n", path, path);
+    // line indices into `a` and `b` at the start of each op
+    let mut at = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for op in &ops {
+        at.push((i, j));
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(ops.len());
+        let range = &ops[start..end];
+        let a_len = range.iter().filter(|o| **o != Op::Insert).count();
+        let b_len = range.iter().filter(|o| **o != Op::Delete).count();
+        let (a_start, b_start) = at[start];
+        // an empty side is numbered by the line before it
+        let line_no = |idx: usize, len: usize| if len == 0 { idx } else { idx + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            line_no(a_start, a_len),
+            a_len,
+            line_no(b_start, b_len),
+            b_len
+        ));
+        for (k, op) in range.iter().enumerate() {
+            let (i, j) = at[start + k];
+            match op {
+                Op::Equal => push_line(&mut out, ' ', a[i]),
+                Op::Delete => push_line(&mut out, '-', a[i]),
+                Op::Insert => push_line(&mut out, '+', b[j]),
+            }
+        }
+    }
+    out
+}
@@ -1,5 +1,13 @@
+mod diff;
+mod report;
+
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use graphrite_core::parser::Parser as CoreParser;
+use graphrite_core::ast::{Direction, EdgeKind, Node, Shape};
+use graphrite_core::error::{Diagnostic, ErrorCode, Severity};
+use graphrite_core::fix::apply_fixes;
+use graphrite_core::parser::{ParseOptions, Parser as CoreParser};
+use graphrite_core::validate::Registry;
+use report::{ColorChoice, Reporter};
 use std::collections::{BTreeMap, VecDeque};
 
 #[derive(Parser)]
@@ -7,6 +15,9 @@ use std::collections::{BTreeMap, VecDeque};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Colourise diagnostics on stderr.
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 }
 
 #[derive(Subcommand)]
@@ -16,8 +27,10 @@ enum Commands {
     Check(CheckArgs),
     Lint(LintArgs),
     Fmt(FmtArgs),
+    Fix(FixArgs),
     Diag(DiagArgs),
     Render(RenderArgs),
+    Explain(ExplainArgs),
 }
 
 #[derive(Args)]
@@ -31,6 +44,11 @@ struct CheckArgs {
     input: Option<String>,
     #[arg(long)]
     diag_json: bool,
+    #[arg(long)]
+    allow_missing_title: bool,
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    deny_warnings: bool,
 }
 #[derive(Args)]
 struct LintArgs {
@@ -39,22 +57,42 @@ struct LintArgs {
     diag_json: bool,
     #[arg(long)]
     pretty: bool,
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    deny_warnings: bool,
 }
 #[derive(Args)]
 struct FmtArgs {
-    input: Option<String>,
-    #[arg(long)]
+    /// Files, or directories searched recursively for `.mmd` and `.graphrite` files;
+    /// reads stdin when omitted.
+    inputs: Vec<String>,
+    /// Rewrite files in place.
+    #[arg(long, conflicts_with = "check")]
     write: bool,
+    /// Print a unified diff of what would change; exit 1 if anything would.
     #[arg(long)]
     check: bool,
 }
 #[derive(Args)]
+struct FixArgs {
+    input: Option<String>,
+    /// Print the fixed source instead of rewriting the file.
+    #[arg(long)]
+    stdout: bool,
+}
+#[derive(Args)]
 struct DiagArgs {
     input: Option<String>,
     #[arg(long)]
     json: bool,
 }
 
+#[derive(Args)]
+struct ExplainArgs {
+    /// Code (`E0203`) or name (`OrphanNode`); lists every code when omitted.
+    code: Option<String>,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum RenderFormat {
     Dot,
@@ -65,6 +103,9 @@ struct RenderArgs {
     input: Option<String>,
     #[arg(long, value_enum, default_value_t = RenderFormat::Dot)]
     format: RenderFormat,
+    /// Guess shapes for plain `id["..."]` nodes from their ids and labels.
+    #[arg(long)]
+    infer_shapes: bool,
 }
 
 fn read_input(path: &Option<String>) -> anyhow::Result<String> {
@@ -77,37 +118,145 @@ fn read_input(path: &Option<String>) -> anyhow::Result<String> {
         Ok(b)
     }
 }
-fn print_pretty(diags: &[graphrite_core::error::Diagnostic]) {
-    for d in diags {
-        if let Some(s) = &d.span {
-            eprintln!(
-                "{}:{}:{} {} {}",
-                s.start.line, s.start.col, d.code, d.message, "^"
-            );
+/// `paths` with every directory replaced by the diagram files below it, in path order.
+fn collect_inputs(paths: &[String]) -> anyhow::Result<Vec<String>> {
+    fn walk(dir: &std::path::Path, out: &mut Vec<String>) -> anyhow::Result<()> {
+        let mut entries = std::fs::read_dir(dir)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for p in entries {
+            if p.is_dir() {
+                walk(&p, out)?;
+            } else if matches!(
+                p.extension().and_then(|e| e.to_str()),
+                Some("mmd" | "graphrite")
+            ) {
+                out.push(p.display().to_string());
+            }
+        }
+        Ok(())
+    }
+    let mut out = Vec::new();
+    for p in paths {
+        let path = std::path::Path::new(p);
+        if path.is_dir() {
+            walk(path, &mut out)?;
         } else {
-            eprintln!("{} {}", d.code, d.message);
+            out.push(p.clone());
         }
     }
+    Ok(out)
 }
 
-fn render_dot(doc: &graphrite_core::ast::Document) -> String {
+fn reporter<'a>(input: &'a Option<String>, src: &'a str, color: ColorChoice) -> Reporter<'a> {
+    Reporter {
+        path: input.as_deref().unwrap_or("<stdin>"),
+        src,
+        color: color.enabled(),
+    }
+}
+
+fn has_failures(diags: &[Diagnostic], deny_warnings: bool) -> bool {
+    diags
+        .iter()
+        .any(|d| d.is_error() || (deny_warnings && d.severity == Severity::Warning))
+}
+
+/// `s` as a quoted DOT string.
+fn dot_str(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `id` as a DOT identifier, quoted when it is a DOT keyword or not a plain name.
+fn dot_id(id: &str) -> String {
+    const KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
+    let plain = id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(id)) {
+        id.to_string()
+    } else {
+        dot_str(id)
+    }
+}
+
+fn render_dot(doc: &graphrite_core::ast::Document, infer: bool) -> String {
     let mut s = String::new();
-    s.push_str("digraph G{\n");
+    s.push_str("digraph G {\n");
+    let rankdir = match doc.directives.direction {
+        Direction::LR => "LR",
+        Direction::RL => "RL",
+        Direction::TD => "TB",
+        Direction::BT => "BT",
+    };
+    s.push_str(&format!("  rankdir={};\n", rankdir));
+    if let Some(title) = doc.metadata.as_ref().and_then(|m| m.title.as_ref()) {
+        s.push_str(&format!("  label={};\n  labelloc=t;\n", dot_str(title)));
+    }
+    s.push_str("  fontname=\"sans-serif\";\n");
+    // defaults are the plain process style; nodes list only what differs from them
+    let (_, fill, stroke) = PLAIN;
+    s.push_str(&format!(
+        "  node [shape=box, style=filled, fillcolor=\"{}\", color=\"{}\", fontname=\"sans-serif\", fontsize=12];\n",
+        fill, stroke
+    ));
+    s.push_str("  edge [color=\"#333333\", fontname=\"sans-serif\", fontsize=11, fontcolor=\"#555555\"];\n");
     for n in &doc.nodes {
-        let l = n.label.replace('"', "\\\"");
-        s.push_str(&format!("  {} [label=\"{}\"];\n", n.id, l));
+        let (shape, f, c) = node_style(n, infer);
+        let mut attrs = vec![format!("label={}", dot_str(&n.label))];
+        match shape {
+            "diamond" | "cylinder" => attrs.push(format!("shape={}", shape)),
+            "stadium" => attrs.push("style=\"rounded,filled\"".to_string()),
+            _ => {}
+        }
+        if (f, c) != (fill, stroke) {
+            attrs.push(format!("fillcolor=\"{}\", color=\"{}\"", f, c));
+        }
+        s.push_str(&format!("  {} [{}];\n", dot_id(&n.id), attrs.join(", ")));
     }
+    dot_clusters(&doc.groups, 1, &mut s);
     for e in &doc.edges {
-        let arrow = match e.kind {
-            graphrite_core::ast::EdgeKind::Flow => "->",
-            graphrite_core::ast::EdgeKind::Conditional => "->",
-        };
-        s.push_str(&format!("  {} {} {};\n", e.from, arrow, e.to));
+        let mut attrs = Vec::new();
+        if let Some(l) = &e.label {
+            attrs.push(format!("label={}", dot_str(l)));
+        }
+        if e.kind == EdgeKind::Conditional {
+            attrs.push("style=dashed".to_string());
+        }
+        let (from, to) = (dot_id(&e.from), dot_id(&e.to));
+        if attrs.is_empty() {
+            s.push_str(&format!("  {} -> {};\n", from, to));
+        } else {
+            s.push_str(&format!("  {} -> {} [{}];\n", from, to, attrs.join(", ")));
+        }
     }
     s.push_str("}\n");
     s
 }
 
+/// `subgraph cluster_<id>` blocks for `groups`, nested like the source and styled like
+/// the SVG group boxes.
+fn dot_clusters(groups: &[graphrite_core::ast::Group], depth: usize, s: &mut String) {
+    let pad = "  ".repeat(depth);
+    for g in groups {
+        let id = dot_id(&format!("cluster_{}", g.id));
+        s.push_str(&format!("{}subgraph {} {{\n", pad, id));
+        s.push_str(&format!("{}  label={};\n", pad, dot_str(&g.label)));
+        s.push_str(&format!(
+            "{}  style=\"rounded,filled\"; fillcolor=\"#f5f7fa\"; color=\"#90a4ae\"; fontcolor=\"#455a64\";\n",
+            pad
+        ));
+        for r in &g.nodes {
+            s.push_str(&format!("{}  {};\n", pad, dot_id(&r.id)));
+        }
+        dot_clusters(&g.groups, depth + 1, s);
+        s.push_str(&format!("{}}}\n", pad));
+    }
+}
+
+/// Shape, fill and stroke of a plain process node.
+const PLAIN: (&str, &str, &str) = ("rect", "#ffffff", "#333333");
+
 fn kind_of(label: &str, id: &str) -> (&'static str, &'static str, &'static str) {
     let l = label.to_ascii_lowercase();
     if id == "start" {
@@ -129,10 +278,26 @@ fn kind_of(label: &str, id: &str) -> (&'static str, &'static str, &'static str)
     } else if l.contains("error") || l.contains("dead letter") {
         ("rect", "#ffebee", "#c62828")
     } else {
-        ("rect", "#ffffff", "#333333")
+        PLAIN
+    }
+}
+
+/// Shape, fill and stroke for `n`. Plain rectangles take the shape [`kind_of`] guesses
+/// when `infer` is set, and stadiums it takes for end terminals turn grey.
+fn node_style(n: &Node, infer: bool) -> (&'static str, &'static str, &'static str) {
+    match n.shape {
+        Shape::Rect if infer => kind_of(&n.label, &n.id),
+        Shape::Rect => PLAIN,
+        Shape::Diamond => ("diamond", "#fff9c4", "#f9a825"),
+        Shape::Cylinder => ("cylinder", "#e3f2fd", "#1565c0"),
+        Shape::Stadium => match kind_of(&n.label, &n.id) {
+            end @ ("stadium", "#eeeeee", _) if infer => end,
+            _ => ("stadium", "#e6ffe6", "#2e7d32"),
+        },
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_shape(
     s: &mut String,
     shape: &str,
@@ -153,7 +318,7 @@ fn layered_positions(
     pad_x: f32,
     pad_y: f32,
 ) -> BTreeMap<&str, (f32, f32)> {
-    let lr = matches!(doc.directives.direction, graphrite_core::ast::Direction::LR);
+    let dir = &doc.directives.direction;
     let mut adj: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
     let mut indeg: BTreeMap<&str, usize> = BTreeMap::new();
     for n in &doc.nodes {
@@ -202,12 +367,28 @@ fn layered_positions(
         let l = *layer.get(n.id.as_str()).unwrap_or(&0);
         buckets.entry(l).or_default().push(&n.id);
     }
+    // keep members of a group next to each other within a layer
+    let mut rank: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut stack: Vec<&graphrite_core::ast::Group> = doc.groups.iter().rev().collect();
+    let mut order = 0;
+    while let Some(g) = stack.pop() {
+        order += 1;
+        for r in &g.nodes {
+            rank.entry(&r.id).or_insert(order);
+        }
+        stack.extend(g.groups.iter().rev());
+    }
+    for ids in buckets.values_mut() {
+        ids.sort_by_key(|id| rank.get(id).copied().unwrap_or(0));
+    }
     let mut pos: BTreeMap<&str, (f32, f32)> = BTreeMap::new();
-    let mut lidx = 0usize;
-    for (_l, ids) in buckets.iter() {
+    let last = buckets.len().saturating_sub(1);
+    for (lidx, ids) in buckets.values().enumerate() {
+        // RL and BT place the first layer at the far end
+        let lidx = if dir.is_reversed() { last - lidx } else { lidx };
         for (i, id) in ids.iter().enumerate() {
             let i = i as f32;
-            let (x, y) = if lr {
+            let (x, y) = if dir.is_horizontal() {
                 (
                     pad_x + (lidx as f32) * (node_w + pad_x),
                     pad_y + i * (node_h + pad_y),
@@ -220,18 +401,71 @@ fn layered_positions(
             };
             pos.insert(id, (x, y));
         }
-        lidx += 1;
     }
     pos
 }
 
-fn render_svg(doc: &graphrite_core::ast::Document) -> String {
+type Rect = [f32; 4];
+
+fn union(a: Option<Rect>, b: Rect) -> Rect {
+    match a {
+        Some(a) => [
+            a[0].min(b[0]),
+            a[1].min(b[1]),
+            a[2].max(b[2]),
+            a[3].max(b[3]),
+        ],
+        None => b,
+    }
+}
+
+/// Boxes (`[x0, y0, x1, y1]`) around the members of `groups` and their nested groups,
+/// appended to `out` parents first so that children are drawn on top. Returns the
+/// extent of all of them.
+///
+/// A box is the bounding box of its members' positions. [`layered_positions`] keeps
+/// members together only within a layer, so a box spanning several layers also covers
+/// any other node placed between its members.
+fn group_boxes<'a>(
+    groups: &'a [graphrite_core::ast::Group],
+    pos: &BTreeMap<&str, (f32, f32)>,
+    (node_w, node_h): (f32, f32),
+    out: &mut Vec<Option<(Rect, &'a graphrite_core::ast::Group)>>,
+) -> Option<Rect> {
+    let mut all = None;
+    for g in groups {
+        let slot = out.len();
+        out.push(None);
+        let mut b = group_boxes(&g.groups, pos, (node_w, node_h), out);
+        for r in &g.nodes {
+            if let Some(&(x, y)) = pos.get(r.id.as_str()) {
+                b = Some(union(b, [x, y, x + node_w, y + node_h]));
+            }
+        }
+        if let Some([x0, y0, x1, y1]) = b {
+            // room for the title above the members
+            let rect = [x0 - 12.0, y0 - 28.0, x1 + 12.0, y1 + 12.0];
+            out[slot] = Some((rect, g));
+            all = Some(union(all, rect));
+        }
+    }
+    all
+}
+
+/// Legend entries per row, and the width of each: a 80px sample plus its label.
+const LEGEND_COLS: usize = 3;
+const LEGEND_COL_W: f32 = 180.0;
+
+/// Stroke pattern of conditional (`-.->`) edges.
+const DASH: &str = " stroke-dasharray=\"6 4\"";
+
+fn render_svg(doc: &graphrite_core::ast::Document, infer: bool) -> String {
     let node_w = 160f32;
     let node_h = 48f32;
     let pad_x = 90f32;
     let pad_y = 70f32;
     let pos = layered_positions(doc, node_w, node_h, pad_x, pad_y);
-    let lr = matches!(doc.directives.direction, graphrite_core::ast::Direction::LR);
+    let lr = doc.directives.direction.is_horizontal();
     let max_x = pos.values().map(|(x, _)| *x).fold(0.0, f32::max);
     let max_y = pos.values().map(|(_, y)| *y).fold(0.0, f32::max);
     let cols = if lr {
@@ -244,51 +478,79 @@ fn render_svg(doc: &graphrite_core::ast::Document) -> String {
     } else {
         ((max_x - pad_x) / (node_w + pad_x)).round() + 1.0
     };
-    let width = (cols * (node_w + pad_x) + pad_x) as i32;
-    let height = (rows * (node_h + pad_y) + pad_y + 120.0) as i32;
+    // legend entries: the node styles, then the dashed conditional edge
+    let mut items = vec![
+        ("Start/End", "stadium", "#e6ffe6", "#2e7d32"),
+        ("Decision", "diamond", "#fff9c4", "#f9a825"),
+        ("Process", "rect", "#ffffff", "#333333"),
+        ("Data/Queue", "cylinder", "#e3f2fd", "#1565c0"),
+    ];
+    // only inference produces the red error style
+    if infer {
+        items.push(("Error", "rect", "#ffebee", "#c62828"));
+    }
+    items.push(("Conditional", "dashed", "", ""));
+    let key_cols = items.len().min(LEGEND_COLS);
+    let key_w = 10.0 + key_cols as f32 * LEGEND_COL_W;
+    let key_h = 20.0 + items.len().div_ceil(LEGEND_COLS) as f32 * 40.0;
+    let keyx = 20.0;
+    let width = (cols * (node_w + pad_x) + pad_x).max(keyx + key_w + 20.0) as i32;
+    let height = (rows * (node_h + pad_y) + pad_y + key_h + 20.0) as i32;
     let mut s = String::new();
     s.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",width,height,width,height));
     s.push_str("  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto-start-reverse\"><path d=\"M0 0 L10 5 L0 10 z\" fill=\"#333\"/></marker></defs>\n");
+    let mut boxes = Vec::new();
+    group_boxes(&doc.groups, &pos, (node_w, node_h), &mut boxes);
+    for ([x0, y0, x1, y1], g) in boxes.into_iter().flatten() {
+        s.push_str(&format!("  <g class=\"group\" id=\"group-{}\">\n", g.id));
+        s.push_str(&format!("    <rect x=\"{:.1}\" y=\"{:.1}\" rx=\"8\" ry=\"8\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#f5f7fa\" stroke=\"#90a4ae\"/>\n", x0, y0, x1 - x0, y1 - y0));
+        s.push_str(&format!("    <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"12\" font-weight=\"bold\" fill=\"#455a64\">{}</text>\n  </g>\n", x0 + 8.0, y0 + 17.0, htmlesc(&g.label)));
+    }
     for e in &doc.edges {
         if let (Some(&(x1, y1)), Some(&(x2, y2))) =
             (pos.get(e.from.as_str()), pos.get(e.to.as_str()))
         {
-            let (sx, sy, tx, ty) = (x1 + node_w, y1 + node_h / 2.0, x2, y2 + node_h / 2.0);
+            // leave the source on the side facing the next layer, enter the target opposite
+            let (sx, sy, tx, ty) = match doc.directives.direction {
+                Direction::LR => (x1 + node_w, y1 + node_h / 2.0, x2, y2 + node_h / 2.0),
+                Direction::RL => (x1, y1 + node_h / 2.0, x2 + node_w, y2 + node_h / 2.0),
+                Direction::TD => (x1 + node_w / 2.0, y1 + node_h, x2 + node_w / 2.0, y2),
+                Direction::BT => (x1 + node_w / 2.0, y1, x2 + node_w / 2.0, y2 + node_h),
+            };
             let mid = if lr { (sx + tx) / 2.0 } else { (sy + ty) / 2.0 };
+            let dash = match e.kind {
+                EdgeKind::Conditional => DASH,
+                EdgeKind::Flow => "",
+            };
             if lr {
-                s.push_str(&format!("  <polyline fill=\"none\" stroke=\"#333\" stroke-width=\"2\" marker-end=\"url(#arrow)\" points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>\n", sx,sy, mid,sy, tx,ty));
+                s.push_str(&format!("  <polyline fill=\"none\" stroke=\"#333\" stroke-width=\"2\"{} marker-end=\"url(#arrow)\" points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>\n", dash, sx,sy, mid,sy, tx,ty));
             } else {
-                s.push_str(&format!("  <polyline fill=\"none\" stroke=\"#333\" stroke-width=\"2\" marker-end=\"url(#arrow)\" points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>\n", sx,sy, sx,mid, tx,ty));
+                s.push_str(&format!("  <polyline fill=\"none\" stroke=\"#333\" stroke-width=\"2\"{} marker-end=\"url(#arrow)\" points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\"/>\n", dash, sx,sy, sx,mid, tx,ty));
+            }
+            if let Some(l) = &e.label {
+                let (lx, ly) = if lr { (mid, sy) } else { (sx, mid) };
+                s.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"11\" text-anchor=\"middle\" dominant-baseline=\"middle\" fill=\"#555\" stroke=\"#fff\" stroke-width=\"3\" paint-order=\"stroke\">{}</text>\n", lx, ly, htmlesc(l)));
             }
         }
     }
     for n in &doc.nodes {
         if let Some(&(x, y)) = pos.get(n.id.as_str()) {
-            let (shape, fill, stroke) = kind_of(&n.label, &n.id);
+            let (shape, fill, stroke) = node_style(n, infer);
             render_shape(&mut s, shape, x, y, node_w, node_h, fill, stroke);
             s.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"12\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>\n", x+node_w/2.0, y+node_h/2.0, htmlesc(&n.label)));
         }
     }
-    let keyx = 20.0;
-    let keyy = (height as f32) - 110.0;
-    s.push_str(&format!("  <g id=\"legend\">\n    <rect x=\"{:.1}\" y=\"{:.1}\" width=\"520\" height=\"100\" fill=\"#fafafa\" stroke=\"#bbb\"/>\n", keyx,keyy));
-    let mut kx = keyx + 10.0;
-    let mut ky = keyy + 20.0;
-    let items = [
-        ("Start/End", "stadium", "#e6ffe6", "#2e7d32"),
-        ("Decision", "diamond", "#fff9c4", "#f9a825"),
-        ("Process", "rect", "#ffffff", "#333333"),
-        ("Data/Queue", "cylinder", "#e3f2fd", "#1565c0"),
-        ("Error", "rect", "#ffebee", "#c62828"),
-    ];
-    for (label, shape, fill, stroke) in items {
-        render_shape(&mut s, shape, kx, ky, 80.0, 30.0, fill, stroke);
-        s.push_str(&format!("    <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"12\">{}</text>\n", kx+90.0, ky+15.0, label));
-        kx += 200.0;
-        if kx > 480.0 {
-            kx = keyx + 10.0;
-            ky += 40.0;
+    let keyy = (height as f32) - key_h - 10.0;
+    s.push_str(&format!("  <g id=\"legend\">\n    <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#fafafa\" stroke=\"#bbb\"/>\n", keyx, keyy, key_w, key_h));
+    for (i, (label, shape, fill, stroke)) in items.into_iter().enumerate() {
+        let kx = keyx + 10.0 + (i % LEGEND_COLS) as f32 * LEGEND_COL_W;
+        let ky = keyy + 20.0 + (i / LEGEND_COLS) as f32 * 40.0;
+        if shape == "dashed" {
+            s.push_str(&format!("    <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#333\" stroke-width=\"2\"{} marker-end=\"url(#arrow)\"/>\n", kx, ky+15.0, kx+80.0, ky+15.0, DASH));
+        } else {
+            render_shape(&mut s, shape, kx, ky, 80.0, 30.0, fill, stroke);
         }
+        s.push_str(&format!("    <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"12\">{}</text>\n", kx+90.0, ky+15.0, label));
     }
     s.push_str("  </g>\n</svg>\n");
     s
@@ -300,36 +562,48 @@ fn htmlesc(s: &str) -> String {
         .replace('>', "&gt;")
 }
 
-fn format_source(src: &str) -> String {
-    let mut out = String::new();
-    for line in src.lines() {
-        let trimmed = line.trim_end();
-        if trimmed.is_empty() {
-            out.push('\n');
-            continue;
-        }
-        let mut l = trimmed.to_string();
-        l = l.replace("-->", " --> ").replace("-.->", " -.-> ");
-        l = l.split_whitespace().collect::<Vec<_>>().join(" ");
-        if let Some(idx) = l.find('[') {
-            if let Some(end) = l[idx + 1..].find(']') {
-                let content = &l[idx + 1..idx + 1 + end];
-                if !(content.starts_with('"') && content.ends_with('"')) {
-                    let before = &l[..idx + 1];
-                    let after = &l[idx + 1 + end..];
-                    let quoted = format!("\"{}\"", content.trim_matches('"'));
-                    l = format!("{}{}{}", before, quoted, after);
-                }
-            }
+/// Applies diagnostic fixes until none are left (fixes can unlock further ones).
+fn fix_source(src: &str) -> (String, usize) {
+    let mut cur = src.to_string();
+    let mut total = 0;
+    for _ in 0..8 {
+        let (_, di) = CoreParser::analyze(&cur, &ParseOptions::default(), &Registry::default());
+        let (next, applied) = apply_fixes(&cur, &di);
+        if applied == 0 {
+            break;
         }
-        out.push_str(&l);
-        out.push('\n');
+        cur = next;
+        total += applied;
     }
-    out
+    (cur, total)
+}
+
+fn explain(code: ErrorCode) -> String {
+    let indent = |src: &str| {
+        src.lines()
+            .map(|l| {
+                if l.is_empty() {
+                    "\n".to_string()
+                } else {
+                    format!("    {}\n", l)
+                }
+            })
+            .collect::<String>()
+    };
+    format!(
+        "{} {}: {}\n\n{}\n\nBad:\n{}\nGood:\n{}",
+        code.code(),
+        code.name(),
+        code.description(),
+        code.explanation(),
+        indent(code.bad_example()),
+        indent(code.good_example())
+    )
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    let color = cli.color;
     match cli.command {
         Commands::Parse(a) | Commands::Ast(a) => {
             let src = read_input(&a.input)?;
@@ -342,7 +616,7 @@ fn main() -> anyhow::Result<()> {
                     if a.diag_json {
                         println!("{}", serde_json::to_string_pretty(&di)?);
                     } else {
-                        print_pretty(&di);
+                        reporter(&a.input, &src, color).emit(&di);
                     }
                     std::process::exit(1)
                 }
@@ -350,97 +624,173 @@ fn main() -> anyhow::Result<()> {
         }
         Commands::Check(a) => {
             let src = read_input(&a.input)?;
-            match CoreParser::parse(&src) {
-                Ok(_) => {
+            let opts = ParseOptions {
+                allow_missing_title: a.allow_missing_title,
+            };
+            let (_, di) = CoreParser::analyze(&src, &opts, &Registry::default());
+            let failed = has_failures(&di, a.deny_warnings);
+            if a.diag_json && !di.is_empty() {
+                println!("{}", serde_json::to_string_pretty(&di)?);
+            } else {
+                reporter(&a.input, &src, color).emit(&di);
+                if !failed {
                     println!("ok");
-                    Ok(())
-                }
-                Err(di) => {
-                    if a.diag_json {
-                        println!("{}", serde_json::to_string_pretty(&di)?);
-                    } else {
-                        print_pretty(&di);
-                    }
-                    std::process::exit(1)
                 }
             }
+            if failed {
+                std::process::exit(1)
+            }
+            Ok(())
         }
         Commands::Lint(a) => {
             let src = read_input(&a.input)?;
-            match CoreParser::parse(&src) {
-                Ok(_) => {
-                    println!("clean");
-                    Ok(())
+            let (_, di) = CoreParser::analyze(&src, &ParseOptions::default(), &Registry::default());
+            if di.is_empty() {
+                println!("clean");
+                return Ok(());
+            }
+            if a.diag_json {
+                println!("{}", serde_json::to_string_pretty(&di)?);
+            } else if a.pretty {
+                reporter(&a.input, &src, color).emit(&di);
+            } else {
+                for d in di.iter() {
+                    println!("{}\t{}", d.code, d.message);
                 }
-                Err(di) => {
-                    if a.diag_json {
-                        println!("{}", serde_json::to_string_pretty(&di)?);
-                    } else if a.pretty {
-                        print_pretty(&di);
-                    } else {
-                        for d in di.iter() {
-                            println!("{}\t{}", d.code, d.message);
+            }
+            if has_failures(&di, a.deny_warnings) {
+                std::process::exit(1)
+            }
+            Ok(())
+        }
+        Commands::Fmt(a) => {
+            let inputs: Vec<Option<String>> = if a.inputs.is_empty() {
+                vec![None]
+            } else {
+                collect_inputs(&a.inputs)?.into_iter().map(Some).collect()
+            };
+            if inputs.len() > 1 && !a.check && !a.write {
+                anyhow::bail!("pass --check or --write to format more than one file");
+            }
+            let (mut changed, mut failed) = (0, 0);
+            for input in &inputs {
+                let src = read_input(input)?;
+                let formatted = match graphrite_core::fmt::format(&src) {
+                    Ok(f) => f,
+                    Err(di) => {
+                        reporter(input, &src, color).emit(&di);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                if formatted != src {
+                    changed += 1;
+                }
+                match input {
+                    _ if a.check => {
+                        let name = input.as_deref().unwrap_or("<stdin>");
+                        print!("{}", diff::unified(name, &src, &formatted));
+                    }
+                    Some(p) if a.write => {
+                        if formatted != src {
+                            std::fs::write(p, &formatted)?;
                         }
                     }
-                    std::process::exit(1)
+                    _ => print!("{}", formatted),
                 }
             }
+            if a.check {
+                eprintln!(
+                    "{} of {} file(s) would be reformatted",
+                    changed,
+                    inputs.len()
+                );
+            } else if a.write && !a.inputs.is_empty() {
+                eprintln!("reformatted {} of {} file(s)", changed, inputs.len());
+            }
+            if failed > 0 {
+                eprintln!("{} file(s) could not be parsed", failed);
+            }
+            if failed > 0 || (a.check && changed > 0) {
+                std::process::exit(1)
+            }
+            Ok(())
         }
-        Commands::Fmt(a) => {
+        Commands::Fix(a) => {
             let src = read_input(&a.input)?;
-            let formatted = format_source(&src);
-            if a.check {
-                if formatted != src {
-                    std::process::exit(1);
-                } else {
-                    println!("ok");
-                    return Ok(());
+            let (fixed, applied) = fix_source(&src);
+            match &a.input {
+                Some(p) if !a.stdout => {
+                    if fixed != src {
+                        std::fs::write(p, &fixed)?;
+                    }
                 }
+                _ => print!("{}", fixed),
             }
-            if a.write {
-                if let Some(p) = a.input {
-                    std::fs::write(p, formatted)?;
-                } else {
-                    print!("{}", formatted);
-                }
-            } else {
-                print!("{}", formatted);
+            let (_, di) =
+                CoreParser::analyze(&fixed, &ParseOptions::default(), &Registry::default());
+            eprintln!(
+                "applied {} fix(es), {} issue(s) remaining",
+                applied,
+                di.len()
+            );
+            reporter(&a.input, &fixed, color).emit(&di);
+            if has_failures(&di, false) {
+                std::process::exit(1)
             }
             Ok(())
         }
         Commands::Diag(a) => {
             let src = read_input(&a.input)?;
-            match CoreParser::parse(&src) {
-                Ok(_) => {
-                    println!("no issues");
+            let (_, di) = CoreParser::analyze(&src, &ParseOptions::default(), &Registry::default());
+            if di.is_empty() {
+                println!("no issues");
+                return Ok(());
+            }
+            if a.json {
+                println!("{}", serde_json::to_string_pretty(&di)?);
+            } else {
+                reporter(&a.input, &src, color).emit(&di);
+            }
+            if has_failures(&di, false) {
+                std::process::exit(1)
+            }
+            Ok(())
+        }
+        Commands::Explain(a) => match a.code {
+            None => {
+                for c in ErrorCode::ALL {
+                    println!("{} {:<32} {}", c.code(), c.name(), c.description());
+                }
+                Ok(())
+            }
+            Some(code) => match ErrorCode::from_code(&code) {
+                Some(c) => {
+                    print!("{}", explain(c));
                     Ok(())
                 }
-                Err(di) => {
-                    if a.json {
-                        println!("{}", serde_json::to_string_pretty(&di)?);
-                    } else {
-                        print_pretty(&di);
-                    }
+                None => {
+                    eprintln!("error: unknown error code '{}'", code);
                     std::process::exit(1)
                 }
-            }
-        }
+            },
+        },
         Commands::Render(a) => {
             let src = read_input(&a.input)?;
             let doc = match CoreParser::parse(&src) {
                 Ok(d) => d,
                 Err(di) => {
-                    print_pretty(&di);
+                    reporter(&a.input, &src, color).emit(&di);
                     std::process::exit(1)
                 }
             };
             match a.format {
                 RenderFormat::Dot => {
-                    print!("{}", render_dot(&doc));
+                    print!("{}", render_dot(&doc, a.infer_shapes));
                     Ok(())
                 }
                 RenderFormat::Svg => {
-                    print!("{}", render_svg(&doc));
+                    print!("{}", render_svg(&doc, a.infer_shapes));
                     Ok(())
                 }
             }
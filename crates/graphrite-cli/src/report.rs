@@ -0,0 +1,162 @@
+use clap::ValueEnum;
+use graphrite_core::ast::Span;
+use graphrite_core::error::{Diagnostic, Severity};
+use std::io::IsTerminal;
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to colour stderr output; `auto` honours `NO_COLOR` and TTY detection.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+fn severity_style(sev: Severity) -> &'static str {
+    match sev {
+        Severity::Error => "\x1b[1;31m",
+        Severity::Warning => "\x1b[1;33m",
+        Severity::Info => "\x1b[1;36m",
+        Severity::Hint => "\x1b[1;32m",
+    }
+}
+
+/// Renders diagnostics against their source, rustc style.
+pub struct Reporter<'a> {
+    pub path: &'a str,
+    pub src: &'a str,
+    pub color: bool,
+}
+
+impl Reporter<'_> {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn render(&self, d: &Diagnostic) -> String {
+        let sev_style = severity_style(d.severity);
+        let mut out = format!(
+            "{}{}\n",
+            self.paint(sev_style, &format!("{}[{}]", d.severity, d.code)),
+            self.paint(BOLD, &format!(": {}", d.message))
+        );
+        let Some(span) = &d.span else {
+            out.push_str(&format!("{} {}\n", self.paint(BLUE, "-->"), self.path));
+            self.notes(d, "", &mut out);
+            return out;
+        };
+        let width = d
+            .related
+            .iter()
+            .map(|r| r.span.start.line)
+            .chain([span.start.line])
+            .max()
+            .unwrap_or(0)
+            .to_string()
+            .len();
+        let pad = " ".repeat(width);
+        out.push_str(&format!("{}{} ", pad, self.paint(BLUE, "-->")));
+        self.snippet(span, &pad, (sev_style, '^'), "", &mut out);
+        for r in &d.related {
+            out.push_str(&format!("{} {}\n", pad, self.paint(BLUE, "|")));
+            out.push_str(&format!("{} {} ", pad, self.paint(BLUE, ":::")));
+            self.snippet(&r.span, &pad, (BLUE, '-'), &r.message, &mut out);
+        }
+        self.notes(d, &pad, &mut out);
+        out
+    }
+
+    /// Location, source line and an underline drawn with the styled `mark`, plus an
+    /// optional label after it.
+    fn snippet(&self, span: &Span, pad: &str, mark: (&str, char), label: &str, out: &mut String) {
+        let bar = self.paint(BLUE, "|");
+        out.push_str(&format!(
+            "{}:{}:{}\n",
+            self.path, span.start.line, span.start.col
+        ));
+        let text = self
+            .src
+            .split('\n')
+            .nth(span.start.line.saturating_sub(1))
+            .unwrap_or("")
+            .trim_end_matches('\r');
+        let start = span.start.col.max(1);
+        let end = if span.end.line == span.start.line {
+            span.end.col.max(start + 1)
+        } else {
+            text.len().max(start) + 1
+        };
+        // columns are byte based; the underline is laid out in characters
+        let width = |s: &str| s.chars().count();
+        let lead = text.get(..start - 1).map_or(start - 1, width);
+        let marks = text
+            .get(start - 1..end - 1)
+            .map_or(end - start, width)
+            .max(1);
+        out.push_str(&format!("{} {}\n", pad, bar));
+        out.push_str(&format!(
+            "{} {} {}\n",
+            self.paint(BLUE, &format!("{:>w$}", span.start.line, w = pad.len())),
+            bar,
+            text
+        ));
+        let label = if label.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", self.paint(BLUE, label))
+        };
+        out.push_str(&format!(
+            "{} {} {}{}{}\n",
+            pad,
+            bar,
+            " ".repeat(lead),
+            self.paint(mark.0, &mark.1.to_string().repeat(marks)),
+            label
+        ));
+    }
+
+    fn notes(&self, d: &Diagnostic, pad: &str, out: &mut String) {
+        let help = d.error_code().map(|c| c.description());
+        if let Some(help) = help.filter(|h| *h != d.message) {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                pad,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, "help:") + " " + help
+            ));
+        }
+        for fix in &d.fixes {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                pad,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, "suggestion:") + " " + &fix.message
+            ));
+        }
+    }
+
+    pub fn emit(&self, diags: &[Diagnostic]) {
+        for d in diags {
+            eprintln!("{}", self.render(d));
+        }
+    }
+}
@@ -3,10 +3,18 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Position {
     pub line: usize,
+    /// 1-based column in UTF-8 bytes.
     pub col: usize,
+    /// 1-based column in UTF-16 code units.
+    #[serde(default)]
+    pub col_utf16: usize,
+    /// Byte offset into the source.
+    #[serde(default)]
+    pub offset: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Source range; `end` is exclusive.
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -21,7 +29,32 @@ pub struct Directives {
 #[serde(rename_all = "UPPERCASE")]
 pub enum Direction {
     LR,
+    RL,
     TD,
+    BT,
+}
+
+impl Direction {
+    /// The direction named in `direction <word>`; `TB` is accepted for `TD`.
+    pub fn from_keyword(word: &str) -> Option<Direction> {
+        match word {
+            "LR" => Some(Direction::LR),
+            "RL" => Some(Direction::RL),
+            "TD" | "TB" => Some(Direction::TD),
+            "BT" => Some(Direction::BT),
+            _ => None,
+        }
+    }
+
+    /// Whether successive ranks are laid out along the x axis.
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Direction::LR | Direction::RL)
+    }
+
+    /// Whether ranks run right to left or bottom to top.
+    pub fn is_reversed(&self) -> bool {
+        matches!(self, Direction::RL | Direction::BT)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -30,11 +63,62 @@ pub struct Metadata {
     pub tags: Option<std::collections::BTreeMap<String, String>>,
 }
 
+/// How a node is drawn, chosen by the brackets around its label.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Shape {
+    /// `id["Label"]`
+    #[default]
+    Rect,
+    /// `id{"Label"}`, for decisions.
+    Diamond,
+    /// `id[("Label")]`, for data stores and queues.
+    Cylinder,
+    /// `id(["Label"])`, for start and end points.
+    Stadium,
+}
+
+impl Shape {
+    pub const ALL: &'static [Shape] =
+        &[Shape::Rect, Shape::Diamond, Shape::Cylinder, Shape::Stadium];
+
+    /// The shape whose opening brackets start with `first`, followed by `second`.
+    pub fn opened_by(first: char, second: Option<char>) -> Option<Shape> {
+        match (first, second) {
+            ('[', Some('(')) => Some(Shape::Cylinder),
+            ('[', _) => Some(Shape::Rect),
+            ('{', _) => Some(Shape::Diamond),
+            ('(', Some('[')) => Some(Shape::Stadium),
+            _ => None,
+        }
+    }
+
+    /// The opening and closing brackets around the label, e.g. `[(` and `)]`.
+    pub fn delimiters(self) -> (&'static str, &'static str) {
+        match self {
+            Shape::Rect => ("[", "]"),
+            Shape::Diamond => ("{", "}"),
+            Shape::Cylinder => ("[(", ")]"),
+            Shape::Stadium => ("([", "])"),
+        }
+    }
+
+    fn is_rect(&self) -> bool {
+        *self == Shape::Rect
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Node {
     pub id: String,
     pub label: String,
+    /// Omitted from JSON for the default [`Shape::Rect`].
+    #[serde(default, skip_serializing_if = "Shape::is_rect")]
+    pub shape: Shape,
     pub span: Option<Span>,
+    /// Span of the quoted label, including the quotes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_span: Option<Span>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -49,6 +133,32 @@ pub struct Edge {
     pub from: String,
     pub to: String,
     pub kind: EdgeKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub span: Option<Span>,
+    /// Span of the label text between the pipes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_span: Option<Span>,
+}
+
+/// A node listed in a [`Group`], by declaring it inside the block or naming it on a line
+/// of its own.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NodeRef {
+    pub id: String,
+    pub span: Option<Span>,
+}
+
+/// A `subgraph id["Title"] ... end` block.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Group {
+    pub id: String,
+    pub label: String,
+    /// Direct members; nodes of nested groups are listed there.
+    pub nodes: Vec<NodeRef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
+    /// Span of the `subgraph id["Title"]` line.
     pub span: Option<Span>,
 }
 
@@ -59,4 +169,7 @@ pub struct Document {
     pub metadata: Option<Metadata>,
     pub nodes: Vec<Node>,
     pub edges: Vec<Edge>,
+    /// Top-level groups, each holding its nested groups.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
 }
@@ -0,0 +1,297 @@
+//! Lossless concrete syntax tree. Every byte of the source, including comments,
+//! blank lines and whitespace, is kept in a leaf, so `root.to_string()` reproduces
+//! the input exactly. [`Document`] is derived from it with [`SyntaxNode::document`].
+use crate::ast::*;
+use crate::lexer::{Token, TokenKind};
+use crate::parser::Parser;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Root,
+    /// Leading comment block and blank lines before `direction`.
+    Header,
+    Directive,
+    NodeDecl,
+    Edge,
+    /// `subgraph id["Title"]`, opening a group.
+    Subgraph,
+    /// A bare node id inside a group, adding that node to it.
+    Member,
+    /// `end`, closing the innermost open group.
+    End,
+    /// A statement, or the rest of one, that did not match the grammar.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(SyntaxNode),
+    /// A lexer token and its exact source text.
+    Token(Token, String),
+    /// Whitespace or skipped text between tokens, with its byte offset.
+    Trivia(usize, String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::Node(n) => n.fmt(f),
+            Element::Token(_, text) | Element::Trivia(_, text) => f.write_str(text),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.children.iter().try_for_each(|c| c.fmt(f))
+    }
+}
+
+impl SyntaxNode {
+    /// Child nodes, in source order.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|c| match c {
+            Element::Node(n) => Some(n),
+            _ => None,
+        })
+    }
+
+    /// Tokens directly under this node, skipping trivia.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|c| match c {
+            Element::Token(t, _) => Some(t),
+            _ => None,
+        })
+    }
+
+    fn span(&self) -> Option<Span> {
+        let first = self.tokens().next()?;
+        let last = self.tokens().last()?;
+        Some(Span {
+            start: first.start(),
+            end: last.end(),
+        })
+    }
+
+    /// The AST for a [`NodeKind::Root`] tree. Statements the parser could not make sense
+    /// of are [`NodeKind::Error`] nodes and contribute nothing.
+    pub fn document(&self) -> Document {
+        let mut doc = Document {
+            version: "1".into(),
+            directives: Directives {
+                direction: Direction::LR,
+            },
+            metadata: None,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            groups: Vec::new(),
+        };
+        // groups being filled, innermost last
+        let mut open: Vec<Group> = Vec::new();
+        for n in self.nodes() {
+            match n.kind {
+                NodeKind::Header => {
+                    // diagnostics for the header come from the parser
+                    doc.metadata = Parser::metadata(n.tokens(), &mut Vec::new())
+                }
+                NodeKind::Directive => {
+                    if let Some(TokenKind::Identifier(d)) = n.tokens().nth(1).map(|t| &t.kind) {
+                        if let Some(dir) = Direction::from_keyword(d) {
+                            doc.directives.direction = dir;
+                        }
+                    }
+                }
+                NodeKind::NodeDecl => {
+                    if let Some(node) = n.node() {
+                        if let Some(g) = open.last_mut() {
+                            g.nodes.push(NodeRef {
+                                id: node.id.clone(),
+                                span: node.span.clone(),
+                            });
+                        }
+                        doc.nodes.push(node);
+                    }
+                }
+                NodeKind::Edge => doc.edges.extend(n.edge()),
+                NodeKind::Subgraph => open.extend(n.group()),
+                NodeKind::Member => {
+                    let first = n.tokens().next();
+                    if let (Some(g), Some(TokenKind::Identifier(id))) =
+                        (open.last_mut(), first.map(|t| &t.kind))
+                    {
+                        g.nodes.push(NodeRef {
+                            id: id.clone(),
+                            span: n.span(),
+                        });
+                    }
+                }
+                NodeKind::End => close(&mut open, &mut doc.groups),
+                NodeKind::Root | NodeKind::Error => {}
+            }
+        }
+        // a missing `end` (E0012) closes at the end of the file
+        while !open.is_empty() {
+            close(&mut open, &mut doc.groups);
+        }
+        doc
+    }
+
+    fn node(&self) -> Option<Node> {
+        let toks: Vec<&Token> = self.tokens().collect();
+        let TokenKind::Identifier(id) = &toks.first()?.kind else {
+            return None;
+        };
+        let delim = |i: usize| toks.get(i).and_then(|t| t.kind.delimiter());
+        let shape = Shape::opened_by(delim(1)?, delim(2))?;
+        // adjacent strings, possibly on several lines, form one label
+        let strings: Vec<(&String, &Token)> = toks
+            .iter()
+            .skip(1 + shape.delimiters().0.len())
+            .map_while(|t| match &t.kind {
+                TokenKind::StringLit(s) => Some(Some((s, *t))),
+                TokenKind::Newline => Some(None),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let label = match (strings.first(), strings.last()) {
+            (Some(first), Some(last)) => Some((
+                strings.iter().map(|s| s.0.as_str()).collect::<String>(),
+                Span {
+                    start: first.1.start(),
+                    end: last.1.end(),
+                },
+            )),
+            _ => None,
+        };
+        Some(Node {
+            id: id.clone(),
+            label: label.as_ref().map(|l| l.0.clone()).unwrap_or_default(),
+            shape,
+            span: self.span(),
+            label_span: label.map(|l| l.1),
+        })
+    }
+
+    fn group(&self) -> Option<Group> {
+        let toks: Vec<&Token> = self.tokens().collect();
+        let TokenKind::Identifier(id) = &toks.get(1)?.kind else {
+            return None;
+        };
+        let label = toks
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::StringLit(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect();
+        Some(Group {
+            id: id.clone(),
+            label,
+            nodes: Vec::new(),
+            groups: Vec::new(),
+            span: self.span(),
+        })
+    }
+
+    fn edge(&self) -> Option<Edge> {
+        let toks: Vec<&Token> = self.tokens().collect();
+        let (TokenKind::Identifier(from), TokenKind::Identifier(to)) =
+            (&toks.first()?.kind, &toks.last()?.kind)
+        else {
+            return None;
+        };
+        let kind = match toks.get(1)?.kind {
+            TokenKind::ArrowCond => EdgeKind::Conditional,
+            _ => EdgeKind::Flow,
+        };
+        let label = match toks.get(3).map(|t| (&t.kind, t)) {
+            // `-->||` has no label
+            Some((TokenKind::LabelText(s) | TokenKind::StringLit(s), t))
+                if matches!(toks[2].kind, TokenKind::Pipe) && !s.is_empty() =>
+            {
+                Some((s.clone(), t.span()))
+            }
+            _ => None,
+        };
+        Some(Edge {
+            from: from.clone(),
+            to: to.clone(),
+            kind,
+            label: label.as_ref().map(|l| l.0.clone()),
+            span: self.span(),
+            label_span: label.map(|l| l.1),
+        })
+    }
+}
+
+/// Moves the innermost open group into its parent, or into `top` if it has none.
+fn close(open: &mut Vec<Group>, top: &mut Vec<Group>) {
+    if let Some(g) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.groups.push(g),
+            None => top.push(g),
+        }
+    }
+}
+
+/// Pushes the trivia between `cursor` and `upto`, if any.
+fn trivia(src: &str, cursor: &mut usize, upto: usize, out: &mut Vec<Element>) {
+    if upto > *cursor {
+        out.push(Element::Trivia(*cursor, src[*cursor..upto].to_string()));
+        *cursor = upto;
+    }
+}
+
+fn leaf(src: &str, cursor: &mut usize, t: &Token, out: &mut Vec<Element>) {
+    trivia(src, cursor, t.offset, out);
+    let end = t.offset + t.len;
+    out.push(Element::Token(t.clone(), src[t.offset..end].to_string()));
+    *cursor = end;
+}
+
+/// Builds the tree for `src` from its tokens and the non-empty `[start, end)` token
+/// ranges of the statements the parser recognised, which must be ordered and
+/// disjoint. Source text between tokens becomes trivia; whitespace before a
+/// statement stays outside it.
+pub(crate) fn build(src: &str, toks: &[Token], marks: &[(NodeKind, usize, usize)]) -> SyntaxNode {
+    debug_assert!(
+        marks.iter().all(|m| m.1 < m.2) && marks.windows(2).all(|w| w[0].2 <= w[1].1),
+        "statement ranges must be non-empty, ordered and disjoint: {:?}",
+        marks
+    );
+    let mut root = Vec::new();
+    let mut cursor = 0usize;
+    let mut marks = marks.iter().peekable();
+    let mut i = 0usize;
+    while i < toks.len() {
+        match marks.peek() {
+            Some(&&(kind, start, end)) if start == i && end > start => {
+                marks.next();
+                trivia(src, &mut cursor, toks[start].offset, &mut root);
+                let mut children = Vec::new();
+                for t in &toks[start..end.min(toks.len())] {
+                    leaf(src, &mut cursor, t, &mut children);
+                }
+                root.push(Element::Node(SyntaxNode { kind, children }));
+                i = end;
+            }
+            _ => {
+                leaf(src, &mut cursor, &toks[i], &mut root);
+                i += 1;
+            }
+        }
+    }
+    trivia(src, &mut cursor, src.len(), &mut root);
+    SyntaxNode {
+        kind: NodeKind::Root,
+        children: root,
+    }
+}
@@ -13,11 +13,465 @@ pub struct Span {
     pub end: Position,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Diagnostic {
+    #[serde(default)]
+    pub severity: Severity,
     pub code: String,
+    /// Symbolic name of `code` (e.g. `OrphanNode`) when it is a built-in code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub message: String,
     pub span: Option<crate::ast::Span>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<Fix>,
+    /// Secondary locations that explain the diagnostic, e.g. a previous declaration.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<Related>,
+}
+
+/// A secondary location attached to a [`Diagnostic`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Related {
+    pub message: String,
+    pub span: crate::ast::Span,
+}
+
+/// Replace the text covered by `span` (end exclusive) with `replacement`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: crate::ast::Span,
+    pub replacement: String,
+}
+
+/// A machine-applicable suggestion; its edits must be applied together.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Fix {
+    pub message: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl Diagnostic {
+    /// An error for `code` with no fixes or related locations, named when `code` is built in.
+    pub fn new(code: &str, message: String, span: Option<crate::ast::Span>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: code.into(),
+            name: ErrorCode::from_code(code).map(|c| c.name().to_string()),
+            message,
+            span,
+            fixes: Vec::new(),
+            related: Vec::new(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        ErrorCode::from_code(&self.code)
+    }
+}
+
+/// Every diagnostic code Graphrite itself emits (see ERROR_CODES.md).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    MissingDirectionFirstLine,
+    MissingDiagramTitle,
+    UnquotedMultiwordLabel,
+    ExpectedBracketAfterLabel,
+    UnexpectedToken,
+    UnclosedSubgraph,
+    MalformedMetaEntry,
+    DuplicateMetaKey,
+    MetaOutsideHeader,
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidEscape,
+    InlineComment,
+    InvalidIdentifierSnakeCase,
+    EdgeFromUnknown,
+    EdgeToUnknown,
+    OrphanNode,
+    DuplicateNodeId,
+    UnknownGroupMember,
+    NodeInMultipleGroups,
+    DuplicateGroupId,
+    ExceedsMaxLineLength,
+    ArrowSpacing,
+    MultipleStatementsPerLine,
+    LongLabel,
+}
+
+/// Catalogue entry backing [`ErrorCode`]'s accessors and `graphrite explain`.
+pub struct CodeInfo {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub explanation: &'static str,
+    pub bad: &'static str,
+    pub good: &'static str,
+}
+
+impl ErrorCode {
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::MissingDirectionFirstLine,
+        ErrorCode::MissingDiagramTitle,
+        ErrorCode::UnquotedMultiwordLabel,
+        ErrorCode::ExpectedBracketAfterLabel,
+        ErrorCode::UnexpectedToken,
+        ErrorCode::UnclosedSubgraph,
+        ErrorCode::MalformedMetaEntry,
+        ErrorCode::DuplicateMetaKey,
+        ErrorCode::MetaOutsideHeader,
+        ErrorCode::UnexpectedCharacter,
+        ErrorCode::UnterminatedString,
+        ErrorCode::InvalidEscape,
+        ErrorCode::InlineComment,
+        ErrorCode::InvalidIdentifierSnakeCase,
+        ErrorCode::EdgeFromUnknown,
+        ErrorCode::EdgeToUnknown,
+        ErrorCode::OrphanNode,
+        ErrorCode::DuplicateNodeId,
+        ErrorCode::UnknownGroupMember,
+        ErrorCode::NodeInMultipleGroups,
+        ErrorCode::DuplicateGroupId,
+        ErrorCode::ExceedsMaxLineLength,
+        ErrorCode::ArrowSpacing,
+        ErrorCode::MultipleStatementsPerLine,
+        ErrorCode::LongLabel,
+    ];
+
+    /// Looks up a code by its number (`E0203`, case-insensitive) or symbolic name (`OrphanNode`).
+    pub fn from_code(code: &str) -> Option<ErrorCode> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| c.code().eq_ignore_ascii_case(code) || c.name().eq_ignore_ascii_case(code))
+    }
+
+    pub fn code(self) -> &'static str {
+        self.info().code
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+
+    pub fn description(self) -> &'static str {
+        self.info().description
+    }
+
+    pub fn explanation(self) -> &'static str {
+        self.info().explanation
+    }
+
+    pub fn bad_example(self) -> &'static str {
+        self.info().bad
+    }
+
+    pub fn good_example(self) -> &'static str {
+        self.info().good
+    }
+
+    pub fn info(self) -> &'static CodeInfo {
+        match self {
+            ErrorCode::MissingDirectionFirstLine => &CodeInfo {
+                code: "E0001",
+                name: "MissingDirectionFirstLine",
+                description: "Direction must be first non-comment line",
+                explanation: "Every diagram declares its layout direction before any node or edge. \
+The first line that is not a comment or blank must be `direction` followed by `LR`, `RL`, \
+`TD` (or its alias `TB`) or `BT`; any other value is rejected rather than guessed.",
+                bad: "%% Diagram: Flow\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::MissingDiagramTitle => &CodeInfo {
+                code: "E0002",
+                name: "MissingDiagramTitle",
+                description: "First comment block must contain %% Diagram: <title>",
+                explanation: "Diagrams are titled so that generated documentation can index them. \
+Add a `%% Diagram: <title>` line to the leading comment block; the title may not be empty. \
+`graphrite check --allow-missing-title` skips this check for drafts.",
+                bad: "direction LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::UnquotedMultiwordLabel => &CodeInfo {
+                code: "E0003",
+                name: "UnquotedMultiwordLabel",
+                description: "Node label must be quoted",
+                explanation: "Labels always appear as a double-quoted string inside the brackets, \
+even single words. Quotes and backslashes inside a label are escaped as \\\" and \\\\.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[Start Here]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"Start Here\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::ExpectedBracketAfterLabel => &CodeInfo {
+                code: "E0010",
+                name: "ExpectedBracketAfterLabel",
+                description: "Missing closing bracket after label",
+                explanation: "A node declaration is `id[\"Label\"]`, or `id{\"Label\"}`, \
+`id[(\"Label\")]` or `id([\"Label\"])` for the other shapes: the quoted label must be \
+followed directly by the brackets matching the opening ones, e.g. `]` for `[`.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::UnexpectedToken => &CodeInfo {
+                code: "E0011",
+                name: "UnexpectedToken",
+                description: "Statement does not match the grammar",
+                explanation: "After the header and `direction`, every line is blank, a comment, a \
+node declaration `id[\"Label\"]`, an edge `a --> b` / `a -->|label| b`, or part of a \
+`subgraph id[\"Title\"] ... end` block. The message names what was expected and what was \
+found; the rest of the line is skipped.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> \"B\"\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::UnclosedSubgraph => &CodeInfo {
+                code: "E0012",
+                name: "UnclosedSubgraph",
+                description: "Subgraph is missing its closing end",
+                explanation: "Every `subgraph id[\"Title\"]` line opens a block that is closed by \
+`end` on a line of its own; nested blocks close innermost first. The diagnostic points at the \
+subgraph that is still open at the end of the file.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\nsubgraph app[\"App\"]\na[\"A\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\nsubgraph app[\"App\"]\n    a[\"A\"]\n    b[\"B\"]\nend\n\na --> b\n",
+            },
+            ErrorCode::MalformedMetaEntry => &CodeInfo {
+                code: "E0020",
+                name: "MalformedMetaEntry",
+                description: "Meta entry is not a key=value pair",
+                explanation: "`%% Meta:` takes a `;`-separated list of `key=value` pairs. Keys and \
+values are trimmed and must both be non-empty; a trailing `;` is allowed.",
+                bad: "%% Diagram: Flow\n%% Meta: type=workflow; draft\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n%% Meta: type=workflow; status=draft\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::DuplicateMetaKey => &CodeInfo {
+                code: "E0021",
+                name: "DuplicateMetaKey",
+                description: "Meta key is defined more than once",
+                explanation: "Meta lines are merged into a single map, so each key may appear only \
+once across all `%% Meta:` lines.",
+                bad: "%% Diagram: Flow\n%% Meta: type=workflow\n%% Meta: type=architecture\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n%% Meta: type=workflow\n%% Meta: domain=social\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::MetaOutsideHeader => &CodeInfo {
+                code: "E0022",
+                name: "MetaOutsideHeader",
+                description: "%% Meta is only allowed in the leading comment block",
+                explanation: "Metadata belongs to the whole diagram and is read from the comment \
+block before `direction`. A `%% Meta:` line further down would be silently ignored by tools, \
+so it is an error.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n%% Meta: type=workflow\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n%% Meta: type=workflow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::UnexpectedCharacter => &CodeInfo {
+                code: "E0030",
+                name: "UnexpectedCharacter",
+                description: "Character cannot start a token",
+                explanation: "Outside strings, labels and comments a line may only contain identifiers, \
+the brackets `[ ] { } ( )`, `|`, quoted strings and the arrows `-->` and `-.->`. Anything \
+else, such as `->`, `;` or an identifier starting with a digit, is reported instead of being \
+silently dropped.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na -> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::UnterminatedString => &CodeInfo {
+                code: "E0031",
+                name: "UnterminatedString",
+                description: "String literal is missing its closing quote",
+                explanation: "Strings cannot span lines: every `\"` must be closed on the same line. \
+A literal quote inside a string is written as \\\".",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::InvalidEscape => &CodeInfo {
+                code: "E0032",
+                name: "InvalidEscape",
+                description: "Unknown escape sequence in string",
+                explanation: "The only escapes inside a string are \\\" for a quote and \\\\ for a \
+backslash. Write other characters, including non-ASCII ones, literally.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\\tB\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A B\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::InlineComment => &CodeInfo {
+                code: "E0033",
+                name: "InlineComment",
+                description: "Comments must be on their own line",
+                explanation: "A `%%` comment must start its line (after optional indentation). \
+Trailing comments after a statement are rejected so that every line holds exactly one \
+statement; `graphrite fix` moves them onto their own line above.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"] %% entry point\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\n%% entry point\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::InvalidIdentifierSnakeCase => &CodeInfo {
+                code: "E0100",
+                name: "InvalidIdentifierSnakeCase",
+                description: "Identifiers must match [a-z][a-z0-9_]* and not end with _",
+                explanation: "Node ids are snake_case: a lowercase letter followed by lowercase \
+letters, digits and underscores, without a trailing underscore. `graphrite fix` renames the \
+node and every edge that refers to it.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\nStartNode[\"Start\"]\nb[\"B\"]\nStartNode --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\nstart_node[\"Start\"]\nb[\"B\"]\nstart_node --> b\n",
+            },
+            ErrorCode::EdgeFromUnknown => &CodeInfo {
+                code: "E0201",
+                name: "EdgeFromUnknown",
+                description: "Edge references unknown source node",
+                explanation: "Both ends of an edge must be declared nodes. Declarations may come \
+before or after the edge, but they must exist somewhere in the file.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\nc --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nc[\"C\"]\na --> b\nc --> b\n",
+            },
+            ErrorCode::EdgeToUnknown => &CodeInfo {
+                code: "E0202",
+                name: "EdgeToUnknown",
+                description: "Edge references unknown destination node",
+                explanation: "Both ends of an edge must be declared nodes. Declarations may come \
+before or after the edge, but they must exist somewhere in the file.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\na --> c\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nc[\"C\"]\na --> b\na --> c\n",
+            },
+            ErrorCode::OrphanNode => &CodeInfo {
+                code: "E0203",
+                name: "OrphanNode",
+                description: "Node has zero incident edges",
+                explanation: "Every declared node must take part in at least one edge. Isolated \
+nodes are usually leftovers from an edit; connect the node or delete it.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nc[\"C\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nc[\"C\"]\na --> b\nb --> c\n",
+            },
+            ErrorCode::DuplicateNodeId => &CodeInfo {
+                code: "E0204",
+                name: "DuplicateNodeId",
+                description: "Node id is declared more than once",
+                explanation: "Each node is declared exactly once; edges refer to it by id. A second \
+declaration would silently replace the first label, so it is an error. The diagnostic points \
+at the repeated declaration and notes where the id was first declared.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"One\"]\nb[\"B\"]\na[\"Two\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"One\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::UnknownGroupMember => &CodeInfo {
+                code: "E0205",
+                name: "UnknownGroupMember",
+                description: "Subgraph lists a node that is not declared",
+                explanation: "A subgraph holds the nodes declared inside it and any node named on \
+a line of its own within it. Named nodes must be declared somewhere in the file.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nsubgraph app[\"App\"]\n    a\n    c\nend\n\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nsubgraph app[\"App\"]\n    a\n    b\nend\n\na --> b\n",
+            },
+            ErrorCode::NodeInMultipleGroups => &CodeInfo {
+                code: "E0206",
+                name: "NodeInMultipleGroups",
+                description: "Node is a member of more than one subgraph",
+                explanation: "Subgraphs form a tree and each node is drawn inside exactly one \
+box, so a node may be listed in at most one subgraph. Nodes of a nested subgraph belong to its \
+parents implicitly and are not listed again. The diagnostic notes the first membership.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nsubgraph one[\"One\"]\n    a\nend\nsubgraph two[\"Two\"]\n    a\nend\n\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\nsubgraph one[\"One\"]\n    a\nend\nsubgraph two[\"Two\"]\n    b\nend\n\na --> b\n",
+            },
+            ErrorCode::DuplicateGroupId => &CodeInfo {
+                code: "E0207",
+                name: "DuplicateGroupId",
+                description: "Subgraph id is already used by a node or subgraph",
+                explanation: "Subgraph ids share one namespace with node ids so that renderers can \
+name every box and node unambiguously. Rename the subgraph.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\nsubgraph a[\"Group\"]\n    a[\"A\"]\n    b[\"B\"]\nend\n\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\nsubgraph group_a[\"Group\"]\n    a[\"A\"]\n    b[\"B\"]\nend\n\na --> b\n",
+            },
+            ErrorCode::ExceedsMaxLineLength => &CodeInfo {
+                code: "E0300",
+                name: "ExceedsMaxLineLength",
+                description: "Line exceeds 100 characters",
+                explanation: "Lines are limited to 100 characters so diagrams stay reviewable in \
+diffs. Shorten the label, or split it into adjacent strings on several lines: \
+`a[\"First half, \"` followed by `    \"second half\"]`. `graphrite fmt` wraps long node \
+declarations this way.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"This label keeps going well past the point where anyone could read it in a side-by-side diff view\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"Short label\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::ArrowSpacing => &CodeInfo {
+                code: "E0310",
+                name: "ArrowSpacing",
+                description: "Arrows must have a single space on each side",
+                explanation: "Edges are written `a --> b`, or `a -->|label| b` with the label \
+attached directly to the arrow. `graphrite fmt` and `graphrite fix` normalise the spacing.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na-->b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::MultipleStatementsPerLine => &CodeInfo {
+                code: "E0311",
+                name: "MultipleStatementsPerLine",
+                description: "Only one statement is allowed per line",
+                explanation: "Each node declaration and edge sits on its own line so that diffs \
+stay line-oriented. `graphrite fmt` and `graphrite fix` split such lines.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"] b[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            },
+            ErrorCode::LongLabel => &CodeInfo {
+                code: "W0301",
+                name: "LongLabel",
+                description: "Node label exceeds 50 characters",
+                explanation: "Long labels crowd rendered diagrams. This is a warning: it is \
+reported by `check` and `lint` but only fails with `--deny-warnings`.",
+                bad: "%% Diagram: Flow\n\ndirection LR\n\na[\"Validate the incoming request payload against the schema\"]\nb[\"B\"]\na --> b\n",
+                good: "%% Diagram: Flow\n\ndirection LR\n\na[\"Validate payload\"]\nb[\"B\"]\na --> b\n",
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Orders diagnostics by source position (unspanned last), then code, drops exact duplicates
+/// and fills in the symbolic name of built-in codes.
+pub fn sort_diagnostics(diags: &mut Vec<Diagnostic>) {
+    diags.sort_by(|a, b| {
+        let key = |d: &Diagnostic| {
+            d.span
+                .as_ref()
+                .map(|s| (s.start.line, s.start.col, s.end.line, s.end.col))
+        };
+        match (key(a), key(b)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+        .then_with(|| a.code.cmp(&b.code))
+        .then_with(|| a.message.cmp(&b.message))
+    });
+    diags.dedup();
+    for d in diags.iter_mut() {
+        if d.name.is_none() {
+            d.name = d.error_code().map(|c| c.name().to_string());
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -0,0 +1,61 @@
+use crate::error::{Diagnostic, Fix, TextEdit};
+
+/// Byte offset of `pos` recomputed from its line and column, to check `pos.offset`.
+fn offset(line_starts: &[usize], src_len: usize, pos: &crate::ast::Position) -> Option<usize> {
+    let start = *line_starts.get(pos.line.checked_sub(1)?)?;
+    let off = start + pos.col.checked_sub(1)?;
+    (off <= src_len).then_some(off)
+}
+
+/// Applies every fix attached to `diags` to `src`, returning the new text and the
+/// number of fixes applied. A fix whose edits overlap an already accepted edit is
+/// skipped as a whole; re-running the parser afterwards will offer it again. So is a
+/// fix with an edit whose byte offsets disagree with its lines and columns, as they do
+/// in spans deserialized without offsets.
+pub fn apply_fixes(src: &str, diags: &[Diagnostic]) -> (String, usize) {
+    apply(src, diags.iter().flat_map(|d| d.fixes.iter()))
+}
+
+pub fn apply<'a>(src: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let resolve = |e: &TextEdit| {
+        let consistent = [&e.span.start, &e.span.end]
+            .into_iter()
+            .all(|pos| offset(&line_starts, src.len(), pos) == Some(pos.offset));
+        let (a, b) = (e.span.start.offset, e.span.end.offset);
+        (consistent && a <= b && src.is_char_boundary(a) && src.is_char_boundary(b))
+            .then_some((a, b))
+    };
+    let mut accepted: Vec<(usize, usize, &str)> = Vec::new();
+    let mut applied = 0usize;
+    for fix in fixes {
+        let Some(ranges) = fix
+            .edits
+            .iter()
+            .map(|e| resolve(e).map(|(a, b)| (a, b, e.replacement.as_str())))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let overlaps = ranges
+            .iter()
+            .any(|&(a, b, _)| accepted.iter().any(|&(x, y, _)| (a < y && x < b) || a == x));
+        if overlaps {
+            continue;
+        }
+        accepted.extend(ranges);
+        applied += 1;
+    }
+    accepted.sort_by_key(|&(a, b, _)| (a, b));
+    let mut out = String::with_capacity(src.len());
+    let mut cursor = 0usize;
+    for (a, b, rep) in accepted {
+        out.push_str(&src[cursor..a]);
+        out.push_str(rep);
+        cursor = b;
+    }
+    out.push_str(&src[cursor..]);
+    (out, applied)
+}
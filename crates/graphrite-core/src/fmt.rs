@@ -0,0 +1,348 @@
+//! Canonical pretty-printer. Output layout:
+//!
+//! ```text
+//! %% Diagram: <title>
+//! %% Meta: ...            (in source order)
+//! %% <other header comments>
+//!
+//! direction LR
+//!
+//! <node declarations and subgraphs>
+//!
+//! <edges>
+//! ```
+//!
+//! Subgraph bodies are indented by four spaces per level. Edges written inside a subgraph
+//! belong to no group, so they move to the edge block.
+//!
+//! Comments stay attached to the statement that follows them (a trailing comment moves
+//! above its statement), and a blank line before a statement is kept within its block.
+//! Node declarations longer than 100 characters are wrapped with the label continued on
+//! the following lines.
+use crate::ast::Shape;
+use crate::cst::{Element, NodeKind, SyntaxNode};
+use crate::error::Diagnostic;
+use crate::lexer::{Token, TokenKind};
+use crate::parser::{ParseOptions, Parser};
+use crate::style;
+use crate::validate::Registry;
+
+/// Longest line the formatter produces, as enforced by E0300.
+const MAX_WIDTH: usize = 100;
+
+/// Diagnostics that mean part of the source is not understood well enough to reprint.
+const BLOCKING: &[&str] = &["E0010", "E0011", "E0012", "E0030", "E0031", "E0032"];
+
+const INDENT: &str = "    ";
+
+struct Stmt {
+    comments: Vec<String>,
+    blank_before: bool,
+    text: String,
+}
+
+/// An entry of the node block.
+enum Item {
+    Stmt(Stmt),
+    /// A subgraph header, its body, and the comments before its `end`.
+    Group(Stmt, Vec<Item>, Vec<String>),
+}
+
+/// Formats `src`, or returns the syntax errors that prevent it.
+pub fn format(src: &str) -> Result<String, Vec<Diagnostic>> {
+    let (_, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::empty());
+    let blocking: Vec<Diagnostic> = diags
+        .into_iter()
+        .filter(|d| BLOCKING.contains(&d.code.as_str()))
+        .collect();
+    if !blocking.is_empty() {
+        return Err(blocking);
+    }
+    Ok(print(&Parser::syntax(src)))
+}
+
+fn comment(text: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() {
+        "%%".into()
+    } else {
+        format!("%% {}", text)
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The declaration `n`, with continuation lines indented for a statement at `indent`.
+fn node_decl(n: &SyntaxNode, indent: &str) -> String {
+    let mut toks = n.tokens();
+    let id = toks.next().map_or("", |t| match &t.kind {
+        TokenKind::Identifier(id) => id.as_str(),
+        _ => "",
+    });
+    let delim = |t: Option<&Token>| t.and_then(|t| t.kind.delimiter());
+    let (first, second) = (delim(toks.next()), delim(toks.next()));
+    let shape = first
+        .and_then(|c| Shape::opened_by(c, second))
+        .unwrap_or_default();
+    let (open, close) = shape.delimiters();
+    // everything between the brackets; an unquoted label is quoted as written
+    let inner: Vec<&Element> = n
+        .children
+        .iter()
+        .skip_while(|c| !matches!(c, Element::Token(t, _) if t.kind.delimiter().is_some()))
+        .skip(open.len())
+        .take_while(
+            |c| !matches!(c, Element::Token(t, _) if t.kind.delimiter() == close.chars().next()),
+        )
+        .collect();
+    let strings: Vec<&str> = inner
+        .iter()
+        .filter_map(|c| match c {
+            Element::Token(t, _) if t.kind != TokenKind::Newline => Some(&t.kind),
+            _ => None,
+        })
+        .map(|k| match k {
+            TokenKind::StringLit(s) => Some(s.as_str()),
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+    let label = match strings.as_slice() {
+        [] => inner
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<String>()
+            .trim()
+            .to_string(),
+        parts => parts.concat(),
+    };
+    wrap_label(&format!("{}{}", id, open), &label, close, indent)
+}
+
+/// `head"label"close`, e.g. `id["label"]`, or when that does not fit in [`MAX_WIDTH`]
+/// characters after `indent`, the label split at spaces into adjacent strings on
+/// continuation lines indented four spaces further. The first line is returned without
+/// `indent`.
+pub(crate) fn wrap_label(head: &str, label: &str, close: &str, indent: &str) -> String {
+    let first = format!("{}{}", indent, head);
+    let cont = format!("{}    ", indent);
+    // room for `close` is kept on every line
+    let fits = |first_line: bool, chunk: &str| {
+        let prefix = if first_line { &first } else { &cont };
+        prefix.chars().count() + quote(chunk).chars().count() + close.len() <= MAX_WIDTH
+    };
+    if fits(true, label) {
+        return format!("{}{}{}", head, quote(label), close);
+    }
+    let mut chunks: Vec<String> = Vec::new();
+    let mut chunk = String::new();
+    for word in label.split_inclusive(' ') {
+        if !chunk.is_empty() && !fits(chunks.is_empty(), &format!("{}{}", chunk, word)) {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        if fits(chunks.is_empty(), &format!("{}{}", chunk, word)) {
+            chunk.push_str(word);
+            continue;
+        }
+        // a word too long for a line of its own is split between characters
+        for ch in word.chars() {
+            if !chunk.is_empty() && !fits(chunks.is_empty(), &format!("{}{}", chunk, ch)) {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+            chunk.push(ch);
+        }
+    }
+    chunks.push(chunk);
+    let lines: Vec<String> = chunks.iter().map(|c| quote(c)).collect();
+    format!("{}{}{}", head, lines.join(&format!("\n{}", cont)), close)
+}
+
+fn edge(n: &SyntaxNode) -> String {
+    let toks: Vec<_> = n.tokens().collect();
+    let name = |i: usize| match toks.get(i).map(|t| &t.kind) {
+        Some(TokenKind::Identifier(id)) => id.as_str(),
+        _ => "",
+    };
+    let arrow = toks.get(1).map_or("-->", |t| style::arrow_text(&t.kind));
+    let label = match toks.get(3).map(|t| &t.kind) {
+        Some(TokenKind::LabelText(s)) if !s.is_empty() => Some(s.clone()),
+        Some(TokenKind::StringLit(s)) if !s.is_empty() => Some(quote(s)),
+        _ => None,
+    };
+    style::edge(name(0), arrow, label.as_deref(), name(toks.len() - 1))
+}
+
+fn subgraph(n: &SyntaxNode, indent: &str) -> String {
+    let id = match n.tokens().nth(1).map(|t| &t.kind) {
+        Some(TokenKind::Identifier(id)) => id.as_str(),
+        _ => "",
+    };
+    let title: String = n
+        .tokens()
+        .filter_map(|t| match &t.kind {
+            TokenKind::StringLit(s) => Some(s.as_str()),
+            _ => None,
+        })
+        .collect();
+    wrap_label(&format!("subgraph {}[", id), &title, "]", indent)
+}
+
+/// The comment directly after the statement ending at `children[i]`, on its last `line`.
+fn trailing_comment(children: &[Element], i: usize, line: usize) -> Option<(usize, String)> {
+    let (j, next) = children
+        .iter()
+        .enumerate()
+        .skip(i + 1)
+        .find(|(_, c)| !matches!(c, Element::Trivia(..)))?;
+    match next {
+        Element::Token(t, _) if t.line == line => match &t.kind {
+            TokenKind::Comment(text) => Some((j, comment(text))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn render(items: Vec<Item>, depth: usize, out: &mut Vec<String>) {
+    let indent = INDENT.repeat(depth);
+    for (i, item) in items.into_iter().enumerate() {
+        let (head, group) = match item {
+            Item::Stmt(s) => (s, None),
+            Item::Group(s, body, close) => (s, Some((body, close))),
+        };
+        if i > 0 && head.blank_before {
+            out.push(String::new());
+        }
+        out.extend(head.comments.iter().map(|c| format!("{}{}", indent, c)));
+        out.push(format!("{}{}", indent, head.text));
+        if let Some((body, close)) = group {
+            render(body, depth + 1, out);
+            out.extend(close.iter().map(|c| format!("{}{}{}", indent, INDENT, c)));
+            out.push(format!("{}end", indent));
+        }
+    }
+}
+
+fn print(root: &SyntaxNode) -> String {
+    let mut title = Vec::new();
+    let mut meta = Vec::new();
+    let mut header = Vec::new();
+    let mut direction = None;
+    let mut edges: Vec<Stmt> = Vec::new();
+    // items of the innermost open group, or of the node block when none is open
+    let mut items: Vec<Item> = Vec::new();
+    // open groups: their header and the items of the enclosing block
+    let mut open: Vec<(Stmt, Vec<Item>)> = Vec::new();
+    let mut pending: Vec<String> = Vec::new();
+    let mut blank = false;
+    let mut line_has_content = false;
+    let mut skip = None;
+    for (i, child) in root.children.iter().enumerate() {
+        if skip == Some(i) {
+            continue;
+        }
+        match child {
+            Element::Node(n) => {
+                line_has_content = true;
+                let line = n.tokens().last().map_or(0, |t| t.line);
+                let indent = INDENT.repeat(open.len());
+                let text = match n.kind {
+                    NodeKind::Header => {
+                        for t in n.tokens() {
+                            if let TokenKind::Comment(text) = &t.kind {
+                                let body = text.trim_start();
+                                if body.starts_with("Diagram:") {
+                                    title.push(comment(text));
+                                } else if body.starts_with("Meta:") {
+                                    meta.push(comment(text));
+                                } else {
+                                    header.push(comment(text));
+                                }
+                            }
+                        }
+                        line_has_content = false;
+                        continue;
+                    }
+                    NodeKind::Directive => {
+                        let words: Vec<String> = n
+                            .children
+                            .iter()
+                            .filter(|c| matches!(c, Element::Token(..)))
+                            .map(|c| c.to_string())
+                            .collect();
+                        direction = Some(words.join(" "));
+                        continue;
+                    }
+                    NodeKind::NodeDecl => node_decl(n, &indent),
+                    NodeKind::Edge => edge(n),
+                    NodeKind::Subgraph => subgraph(n, &indent),
+                    NodeKind::Member | NodeKind::End => n.to_string(),
+                    NodeKind::Root | NodeKind::Error => continue,
+                };
+                let mut stmt = Stmt {
+                    comments: std::mem::take(&mut pending),
+                    blank_before: std::mem::take(&mut blank),
+                    text,
+                };
+                // a trailing comment goes above the statement it follows
+                if let Some((j, c)) = trailing_comment(&root.children, i, line) {
+                    stmt.comments.push(c);
+                    skip = Some(j);
+                }
+                match n.kind {
+                    NodeKind::Edge => edges.push(stmt),
+                    NodeKind::Subgraph => open.push((stmt, std::mem::take(&mut items))),
+                    NodeKind::End => {
+                        if let Some((head, parent)) = open.pop() {
+                            let body = std::mem::replace(&mut items, parent);
+                            items.push(Item::Group(head, body, stmt.comments));
+                        }
+                    }
+                    _ => items.push(Item::Stmt(stmt)),
+                }
+            }
+            Element::Token(t, _) => match &t.kind {
+                TokenKind::Comment(text) => {
+                    pending.push(comment(text));
+                    line_has_content = true;
+                }
+                TokenKind::Newline => {
+                    if !line_has_content && (direction.is_some() || !items.is_empty()) {
+                        blank = true;
+                    }
+                    line_has_content = false;
+                }
+                _ => {}
+            },
+            Element::Trivia(..) => {}
+        }
+    }
+    let mut sections: Vec<Vec<String>> = Vec::new();
+    let head: Vec<String> = title.into_iter().chain(meta).chain(header).collect();
+    sections.push(head);
+    sections.push(direction.into_iter().collect());
+    let mut nodes = Vec::new();
+    render(items, 0, &mut nodes);
+    sections.push(nodes);
+    let mut edge_lines = Vec::new();
+    render(
+        edges.into_iter().map(Item::Stmt).collect(),
+        0,
+        &mut edge_lines,
+    );
+    sections.push(edge_lines);
+    sections.push(pending);
+    let mut out = String::new();
+    for section in sections.into_iter().filter(|s| !s.is_empty()) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        for line in section {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -1,193 +1,382 @@
+use crate::ast::{Position, Span};
+use crate::error::{Diagnostic, Fix, Severity, TextEdit};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenKind {
     DirectionKw,
     Identifier(String),
     StringLit(String),
-    ArrowFlow, // -->
-    ArrowCond, // -.- > (represented as -.->)
-    LBracket,  // [
-    RBracket,  // ]
-    Pipe,      // |
+    ArrowFlow,         // -->
+    ArrowCond,         // -.- > (represented as -.->)
+    LBracket,          // [
+    RBracket,          // ]
+    LBrace,            // {
+    RBrace,            // }
+    LParen,            // (
+    RParen,            // )
+    Pipe,              // |
+    LabelText(String), // raw text between pipes: -->|text|
     Comment(String),
     Newline,
     Eof,
 }
 
+impl TokenKind {
+    /// The bracket character of a `[`, `]`, `{`, `}`, `(` or `)` token.
+    pub fn delimiter(&self) -> Option<char> {
+        match self {
+            TokenKind::LBracket => Some('['),
+            TokenKind::RBracket => Some(']'),
+            TokenKind::LBrace => Some('{'),
+            TokenKind::RBrace => Some('}'),
+            TokenKind::LParen => Some('('),
+            TokenKind::RParen => Some(')'),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     pub kind: TokenKind,
     pub line: usize,
+    /// 1-based column in UTF-8 bytes.
     pub col: usize,
+    /// 1-based column in UTF-16 code units, as used by LSP clients.
+    pub col_utf16: usize,
+    /// Byte offset of the first byte of the token in the input.
+    pub offset: usize,
+    /// Length of the token's source text in bytes.
+    pub len: usize,
+    /// Length of the token's source text in UTF-16 code units.
+    pub len_utf16: usize,
+    /// Spaces and tabs between the previous token on this line (or the line start)
+    /// and this one.
+    pub ws_before: String,
+}
+
+impl Token {
+    pub fn start(&self) -> Position {
+        Position {
+            line: self.line,
+            col: self.col,
+            col_utf16: self.col_utf16,
+            offset: self.offset,
+        }
+    }
+
+    /// Position just past the token.
+    pub fn end(&self) -> Position {
+        Position {
+            line: self.line,
+            col: self.col + self.len,
+            col_utf16: self.col_utf16 + self.len_utf16,
+            offset: self.offset + self.len,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start(),
+            end: self.end(),
+        }
+    }
+}
+
+/// Number of UTF-16 code units in `s`.
+pub fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+/// One line of input being scanned; positions are byte indices into `text`.
+struct Line<'a> {
+    text: &'a str,
+    no: usize,
+    offset: usize,
+}
+
+impl Line<'_> {
+    fn position(&self, at: usize) -> Position {
+        Position {
+            line: self.no,
+            col: at + 1,
+            col_utf16: utf16_len(&self.text[..at]) + 1,
+            offset: self.offset + at,
+        }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            start: self.position(start),
+            end: self.position(end),
+        }
+    }
+
+    fn error(&self, code: &str, message: String, start: usize, end: usize) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: code.into(),
+            message,
+            span: Some(self.span(start, end)),
+            fixes: Vec::new(),
+            name: None,
+            related: Vec::new(),
+        }
+    }
+
+    /// E0033 for a `%%` comment at `at` that follows code on the same line, with a
+    /// fix moving it onto its own line above.
+    fn inline_comment(&self, at: usize) -> Diagnostic {
+        let body = self.text.trim_end_matches('\n').trim_end_matches('\r');
+        let code_end = body[..at].trim_end().len();
+        let indent = &body[..body.len() - body.trim_start().len()];
+        let mut d = self.error(
+            "E0033",
+            "Comments must be on their own line".into(),
+            at,
+            body.len(),
+        );
+        d.fixes.push(Fix {
+            message: "move the comment to its own line".into(),
+            edits: vec![
+                TextEdit {
+                    span: self.span(0, 0),
+                    replacement: format!("{}{}\n", indent, &body[at..]),
+                },
+                TextEdit {
+                    span: self.span(code_end, body.len()),
+                    replacement: String::new(),
+                },
+            ],
+        });
+        d
+    }
+
+    fn token(&self, kind: TokenKind, start: usize, end: usize) -> Token {
+        let before = &self.text[..start];
+        Token {
+            ws_before: before[before.trim_end_matches([' ', '\t']).len()..].to_string(),
+            kind,
+            line: self.no,
+            col: start + 1,
+            col_utf16: utf16_len(&self.text[..start]) + 1,
+            offset: self.offset + start,
+            len: end - start,
+            len_utf16: utf16_len(&self.text[start..end]),
+        }
+    }
 }
 
 pub struct Lexer;
 
 impl Lexer {
     pub fn tokenize(input: &str) -> Vec<Token> {
+        Self::lex(input).0
+    }
+
+    /// Tokenizes `input`, reporting characters that cannot start a token (E0030),
+    /// unterminated strings (E0031), unknown escapes (E0032) and inline comments
+    /// (E0033). Offending text is skipped so parsing can continue.
+    pub fn lex(input: &str) -> (Vec<Token>, Vec<Diagnostic>) {
         let mut toks = Vec::new();
+        let mut diags = Vec::new();
         let mut line_no = 1usize;
-        for line in input.split_inclusive('\n') {
+        let mut line_off = 0usize;
+        for text in input.split_inclusive('\n') {
+            let line = Line {
+                text,
+                no: line_no,
+                offset: line_off,
+            };
+            let len = text.len();
+            let body = text.trim_end_matches('\n');
             let mut i = 0usize;
-            let bytes = line.as_bytes();
-            let len = bytes.len();
-            while i < len {
-                let col = i + 1;
-                let c = bytes[i] as char;
+            while let Some(c) = text[i..].chars().next() {
                 match c {
-                    ' ' | '\t' => {
+                    ' ' | '\t' | '\r' => {
                         i += 1;
                     }
                     '\n' => {
-                        toks.push(Token {
-                            kind: TokenKind::Newline,
-                            line: line_no,
-                            col,
-                        });
+                        toks.push(line.token(TokenKind::Newline, i, i + 1));
                         i += 1;
                     }
-                    '%' if i + 1 < len && bytes[i + 1] as char == '%' => {
-                        let text = &line[i + 2..];
-                        let text = text.trim_end_matches('\n').to_string();
-                        toks.push(Token {
-                            kind: TokenKind::Comment(text),
-                            line: line_no,
-                            col,
-                        });
+                    '%' if text[i + 1..].starts_with('%') => {
+                        let comment = body[i + 2..].to_string();
+                        toks.push(line.token(TokenKind::Comment(comment), i, body.len()));
+                        if !body[..i].trim().is_empty() {
+                            diags.push(line.inline_comment(i));
+                        }
                         // consume rest of line including optional trailing \n via outer split
                         i = len;
                     }
-                    '[' => {
-                        toks.push(Token {
-                            kind: TokenKind::LBracket,
-                            line: line_no,
-                            col,
-                        });
-                        i += 1;
-                    }
-                    ']' => {
-                        toks.push(Token {
-                            kind: TokenKind::RBracket,
-                            line: line_no,
-                            col,
-                        });
+                    '[' | ']' | '{' | '}' | '(' | ')' => {
+                        let kind = match c {
+                            '[' => TokenKind::LBracket,
+                            ']' => TokenKind::RBracket,
+                            '{' => TokenKind::LBrace,
+                            '}' => TokenKind::RBrace,
+                            '(' => TokenKind::LParen,
+                            _ => TokenKind::RParen,
+                        };
+                        toks.push(line.token(kind, i, i + 1));
                         i += 1;
                     }
                     '|' => {
-                        toks.push(Token {
-                            kind: TokenKind::Pipe,
-                            line: line_no,
-                            col,
-                        });
+                        toks.push(line.token(TokenKind::Pipe, i, i + 1));
                         i += 1;
+                        // edge label: raw text up to the closing pipe, without the spaces
+                        // around it; quoted labels fall through to the regular string
+                        // literal handling
+                        if let Some(off) = body[i..].find('|') {
+                            let raw = &body[i..i + off];
+                            let label = raw.trim();
+                            if !label.starts_with('"') {
+                                let start = i + raw.len() - raw.trim_start().len();
+                                toks.push(line.token(
+                                    TokenKind::LabelText(label.to_string()),
+                                    start,
+                                    start + label.len(),
+                                ));
+                                toks.push(line.token(TokenKind::Pipe, i + off, i + off + 1));
+                                i += off + 1;
+                            }
+                        }
                     }
                     '"' => {
                         // string literal until next unescaped quote or EOL
-                        let mut j = i + 1;
                         let mut s = String::new();
-                        while j < len {
-                            let ch = bytes[j] as char;
-                            if ch == '"' {
-                                break;
-                            }
-                            if ch == '\\' && j + 1 < len {
-                                let nxt = bytes[j + 1] as char;
-                                match nxt {
-                                    '"' => {
-                                        s.push('"');
-                                        j += 2;
-                                        continue;
+                        let mut chars = body[i + 1..].char_indices();
+                        let mut end = None;
+                        while let Some((j, ch)) = chars.next() {
+                            match ch {
+                                '"' => {
+                                    end = Some(i + 1 + j + 1);
+                                    break;
+                                }
+                                '\\' => match chars.clone().next() {
+                                    Some((_, nxt @ ('"' | '\\'))) => {
+                                        s.push(nxt);
+                                        chars.next();
                                     }
-                                    '\\' => {
-                                        s.push('\\');
-                                        j += 2;
-                                        continue;
+                                    Some((_, nxt)) => {
+                                        let at = i + 1 + j;
+                                        diags.push(line.error(
+                                            "E0032",
+                                            format!("Invalid escape '\\{}' in string", nxt),
+                                            at,
+                                            at + 1 + nxt.len_utf8(),
+                                        ));
+                                        s.push(ch);
                                     }
-                                    _ => {}
-                                }
+                                    None => s.push(ch),
+                                },
+                                _ => s.push(ch),
                             }
-                            s.push(ch);
-                            j += 1;
                         }
-                        toks.push(Token {
-                            kind: TokenKind::StringLit(s),
-                            line: line_no,
-                            col,
+                        let end = end.unwrap_or_else(|| {
+                            let eol = body.trim_end_matches('\r').len();
+                            diags.push(line.error(
+                                "E0031",
+                                "Unterminated string literal".into(),
+                                i,
+                                eol,
+                            ));
+                            eol
                         });
-                        i = if j < len { j + 1 } else { len };
+                        toks.push(line.token(TokenKind::StringLit(s), i, end));
+                        i = end;
                     }
                     '-' => {
                         // arrows
-                        if i + 2 < len && bytes[i + 1] as char == '-' && bytes[i + 2] as char == '>'
-                        {
-                            toks.push(Token {
-                                kind: TokenKind::ArrowFlow,
-                                line: line_no,
-                                col,
-                            });
+                        if text[i..].starts_with("-->") {
+                            toks.push(line.token(TokenKind::ArrowFlow, i, i + 3));
                             i += 3;
-                        } else if i + 3 < len
-                            && bytes[i + 1] as char == '.'
-                            && bytes[i + 2] as char == '-'
-                            && bytes[i + 3] as char == '>'
-                        {
-                            toks.push(Token {
-                                kind: TokenKind::ArrowCond,
-                                line: line_no,
-                                col,
-                            });
+                        } else if text[i..].starts_with("-.->") {
+                            toks.push(line.token(TokenKind::ArrowCond, i, i + 4));
                             i += 4;
+                        } else if text[i..].starts_with("->") {
+                            let mut d = line.error(
+                                "E0030",
+                                "Unexpected '->'; arrows are '-->' or '-.->'".into(),
+                                i,
+                                i + 2,
+                            );
+                            d.fixes.push(Fix {
+                                message: "use '-->'".into(),
+                                edits: vec![TextEdit {
+                                    span: line.span(i, i + 2),
+                                    replacement: "-->".into(),
+                                }],
+                            });
+                            diags.push(d);
+                            i += 2;
                         } else {
+                            diags.push(line.error(
+                                "E0030",
+                                "Unexpected character '-'".into(),
+                                i,
+                                i + 1,
+                            ));
                             i += 1;
                         }
                     }
                     _ => {
                         // identifier/keywords
                         if c.is_ascii_alphabetic() {
-                            let mut j = i + 1;
-                            while j < len {
-                                let ch = bytes[j] as char;
-                                if ch.is_ascii_alphanumeric() || ch == '_' {
-                                    j += 1;
-                                } else {
-                                    break;
-                                }
-                            }
-                            let word = &line[i..j];
-                            let word_str = word.trim_end_matches('\n');
-                            if word_str == "direction" {
-                                toks.push(Token {
-                                    kind: TokenKind::DirectionKw,
-                                    line: line_no,
-                                    col,
-                                });
+                            let j = text[i..]
+                                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                                .map_or(len, |off| i + off);
+                            let word = &text[i..j];
+                            let kind = if word == "direction" {
+                                TokenKind::DirectionKw
                             } else {
-                                toks.push(Token {
-                                    kind: TokenKind::Identifier(word_str.to_string()),
-                                    line: line_no,
-                                    col,
-                                });
-                            }
+                                TokenKind::Identifier(word.to_string())
+                            };
+                            toks.push(line.token(kind, i, j));
+                            i = j;
+                        } else if c.is_ascii_digit() || c == '_' {
+                            let j = text[i..]
+                                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                                .map_or(len, |off| i + off);
+                            diags.push(line.error(
+                                "E0030",
+                                format!(
+                                    "Unexpected '{}'; identifiers start with a letter",
+                                    &text[i..j]
+                                ),
+                                i,
+                                j,
+                            ));
                             i = j;
                         } else {
-                            i += 1;
+                            let end = i + c.len_utf8();
+                            diags.push(line.error(
+                                "E0030",
+                                format!("Unexpected character '{}'", c.escape_default()),
+                                i,
+                                end,
+                            ));
+                            i = end;
                         }
                     }
                 }
             }
-            if !line.ends_with('\n') {
-                toks.push(Token {
-                    kind: TokenKind::Newline,
-                    line: line_no,
-                    col: 1,
-                });
+            if !text.ends_with('\n') {
+                toks.push(line.token(TokenKind::Newline, len, len));
             }
             line_no += 1;
+            line_off += len;
         }
         toks.push(Token {
             kind: TokenKind::Eof,
             line: line_no,
             col: 1,
+            col_utf16: 1,
+            offset: input.len(),
+            len: 0,
+            len_utf16: 0,
+            ws_before: String::new(),
         });
-        toks
+        (toks, diags)
     }
 }
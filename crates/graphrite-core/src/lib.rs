@@ -1,4 +1,9 @@
 pub mod ast;
+pub mod cst;
 pub mod error;
+pub mod fix;
+pub mod fmt;
 pub mod lexer;
 pub mod parser;
+pub mod style;
+pub mod validate;
@@ -1,25 +1,234 @@
 use crate::ast::*;
-use crate::error::Diagnostic;
-use crate::lexer::{Lexer, Token, TokenKind};
+use crate::cst::{self, NodeKind, SyntaxNode};
+use crate::error::{sort_diagnostics, Diagnostic, Fix, Severity, TextEdit};
+use crate::fmt;
+use crate::lexer::{utf16_len, Lexer, Token, TokenKind};
+use crate::style;
+use crate::validate::Registry;
+use std::collections::BTreeMap;
+
+/// Words with a meaning of their own in statement position, never offered as new ids.
+const RESERVED: &[&str] = &["direction", "subgraph", "end"];
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Accept documents without a `%% Diagram: <title>` header (E0002).
+    pub allow_missing_title: bool,
+}
 
 pub struct Parser {
+    src: String,
     toks: Vec<Token>,
     idx: usize,
+    opts: ParseOptions,
+    /// Lexer diagnostics, reported after parsing unless superseded (see E0003).
+    lex_diags: Vec<Diagnostic>,
+    /// Token ranges of the statements recognised so far, for the CST.
+    marks: Vec<(NodeKind, usize, usize)>,
+    /// First token of the statement being parsed.
+    stmt_start: usize,
+    /// `subgraph` keyword tokens of the blocks not yet closed by `end`, innermost last.
+    open: Vec<usize>,
 }
 
 impl Parser {
     pub fn parse(src: &str) -> Result<Document, Vec<Diagnostic>> {
-        let toks = Lexer::tokenize(src);
-        let mut p = Parser { toks, idx: 0 };
-        p.document()
+        Self::parse_with(src, &ParseOptions::default())
+    }
+
+    pub fn parse_with(src: &str, opts: &ParseOptions) -> Result<Document, Vec<Diagnostic>> {
+        Self::parse_with_rules(src, opts, &Registry::default())
+    }
+
+    /// Parses `src` and runs every rule in `rules` over the result. Succeeds unless an
+    /// error-severity diagnostic is produced; warnings are only visible through [`Parser::analyze`].
+    pub fn parse_with_rules(src: &str, opts: &ParseOptions, rules: &Registry) -> Result<Document, Vec<Diagnostic>> {
+        let (doc, diags) = Self::analyze(src, opts, rules);
+        if diags.iter().any(Diagnostic::is_error) { return Err(diags); }
+        Ok(doc)
+    }
+
+    /// Parses and validates `src`, returning the (possibly partial) document with every diagnostic.
+    pub fn analyze(src: &str, opts: &ParseOptions, rules: &Registry) -> (Document, Vec<Diagnostic>) {
+        let (root, mut diags) = Self::run(src, opts);
+        let doc = root.document();
+        diags.extend(rules.check(&doc));
+        sort_diagnostics(&mut diags);
+        (doc, diags)
+    }
+
+    /// The lossless syntax tree of `src`; `syntax(src).to_string() == src` for any input.
+    pub fn syntax(src: &str) -> SyntaxNode {
+        Self::run(src, &ParseOptions::default()).0
+    }
+
+    fn run(src: &str, opts: &ParseOptions) -> (SyntaxNode, Vec<Diagnostic>) {
+        let (toks, lex_diags) = Lexer::lex(src);
+        let mut p = Parser { src: src.to_string(), toks, idx: 0, opts: opts.clone(), lex_diags, marks: Vec::new(), stmt_start: 0, open: Vec::new() };
+        let diags = p.document();
+        (cst::build(src, &p.toks, &p.marks), diags)
     }
 
     fn peek(&self) -> &Token { &self.toks[self.idx] }
+    fn delim(&self, i: usize) -> Option<char> { self.toks.get(i).and_then(|t| t.kind.delimiter()) }
     fn bump(&mut self) { if self.idx < self.toks.len() - 1 { self.idx += 1; } }
     fn expect_newline(&mut self) { if matches!(self.peek().kind, TokenKind::Newline) { self.bump(); } }
 
-    fn make_span(tok: &Token, end_tok: &Token) -> Span {
-        Span { start: Position { line: tok.line, col: tok.col }, end: Position { line: end_tok.line, col: end_tok.col } }
+    fn start_of(t: &Token) -> Position { t.start() }
+    fn end_of(t: &Token) -> Position { t.end() }
+
+    /// Span from the start of `tok` to the end of `end_tok` (exclusive).
+    fn make_span(tok: &Token, end_tok: &Token) -> Span { Span { start: Self::start_of(tok), end: Self::end_of(end_tok) } }
+    fn tok_span(t: &Token) -> Span { Self::make_span(t, t) }
+
+    /// Span of the whole of `line`, excluding its line terminator.
+    fn line_span(&self, line: usize) -> Span {
+        let offset: usize = self.src.split_inclusive('\n').take(line.saturating_sub(1)).map(str::len).sum();
+        let text = self.line_text(line).trim_end_matches('\r');
+        Span { start: Position { line, col: 1, col_utf16: 1, offset }, end: Position { line, col: text.len() + 1, col_utf16: utf16_len(text) + 1, offset: offset + text.len() } }
+    }
+
+    /// Consumes `|label|` between an arrow and its destination, if complete.
+    fn edge_label(&mut self) -> bool {
+        let label = matches!(self.toks.get(self.idx+1).map(|t| &t.kind), Some(TokenKind::LabelText(_) | TokenKind::StringLit(_)));
+        let closed = matches!(self.toks.get(self.idx+2).map(|t| &t.kind), Some(TokenKind::Pipe));
+        if !(matches!(self.peek().kind, TokenKind::Pipe) && label && closed) { return false; }
+        self.bump(); self.bump(); self.bump();
+        true
+    }
+
+    /// label := STRING (NEWLINE? STRING)*, called after the first STRING. Adjacent strings are
+    /// joined, which lets a long label continue on the next line.
+    fn label_rest(&mut self) {
+        loop {
+            let next = if matches!(self.peek().kind, TokenKind::Newline) { self.idx + 1 } else { self.idx };
+            if !matches!(self.toks.get(next).map(|t| &t.kind), Some(TokenKind::StringLit(_))) { return; }
+            self.idx = next + 1;
+        }
+    }
+
+    /// Title and tags of the `%% Diagram:` and `%% Meta:` comments among `toks`, reporting
+    /// malformed and duplicate meta entries. The only reader of the header, for the parser and
+    /// for [`SyntaxNode::document`] alike.
+    pub(crate) fn metadata<'a>(toks: impl IntoIterator<Item = &'a Token>, diags: &mut Vec<Diagnostic>) -> Option<Metadata> {
+        let mut title = None;
+        let mut tags: Option<BTreeMap<String, String>> = None;
+        for t in toks {
+            let TokenKind::Comment(text) = &t.kind else { continue };
+            let body = text.trim_start();
+            if let Some(rest) = body.strip_prefix("Diagram:") { title = Some(rest.trim().to_string()); }
+            else if body.starts_with("Meta:") { Self::meta_tags(text, t, tags.get_or_insert_with(BTreeMap::new), diags); }
+        }
+        (title.is_some() || tags.is_some()).then_some(Metadata{ title, tags })
+    }
+
+    fn meta_tags(text: &str, tok: &Token, tags: &mut BTreeMap<String, String>, diags: &mut Vec<Diagnostic>) {
+        let body_off = text.find("Meta:").map(|i| i + "Meta:".len()).unwrap_or(0);
+        let mut off = body_off;
+        for entry in text[body_off..].split(';') {
+            let entry_off = off;
+            off += entry.len() + 1;
+            if entry.trim().is_empty() { continue; }
+            let lead = entry.len() - entry.trim_start().len();
+            let (col, offset, len) = (tok.col + 2 + entry_off + lead, tok.offset + 2 + entry_off + lead, entry.trim().len());
+            let col_utf16 = tok.col_utf16 + 2 + utf16_len(&text[..entry_off + lead]);
+            let span = Some(Span{ start: Position{ line: tok.line, col, col_utf16, offset }, end: Position{ line: tok.line, col: col + len, col_utf16: col_utf16 + utf16_len(entry.trim()), offset: offset + len } });
+            let (key, value) = match entry.split_once('=') { Some((k, v)) => (k.trim(), v.trim()), None => ("", "") };
+            if key.is_empty() || value.is_empty() { diags.push(Diagnostic{ severity: Severity::Error, code:"E0020".into(), message: format!("Malformed meta entry '{}', expected key=value", entry.trim()), span, fixes: Vec::new(), name: None, related: Vec::new() }); continue; }
+            if tags.contains_key(key) { diags.push(Diagnostic{ severity: Severity::Error, code:"E0021".into(), message: format!("Duplicate meta key '{}'", key), span, fixes: Vec::new(), name: None, related: Vec::new() }); continue; }
+            tags.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    fn line_text(&self, line: usize) -> &str {
+        self.src.split('\n').nth(line.saturating_sub(1)).unwrap_or("")
+    }
+
+    fn to_snake_case(id: &str) -> Option<String> {
+        let mut out = String::new();
+        let mut prev: Option<char> = None;
+        for ch in id.chars() {
+            if ch.is_ascii_uppercase() {
+                if matches!(prev, Some(p) if p.is_ascii_lowercase() || p.is_ascii_digit()) { out.push('_'); }
+                out.push(ch.to_ascii_lowercase());
+            } else if ch.is_ascii_lowercase() || ch.is_ascii_digit() { out.push(ch); }
+            else if !out.is_empty() && !out.ends_with('_') { out.push('_'); }
+            prev = Some(ch);
+        }
+        let out = out.trim_end_matches('_').to_string();
+        Self::is_snake_case(&out).then_some(out)
+    }
+
+    fn collapse_ws(line: &str) -> String {
+        let mut out = String::new();
+        let mut in_str = false;
+        let mut chars = line.trim().chars();
+        while let Some(ch) = chars.next() {
+            if in_str {
+                out.push(ch);
+                if ch == '\\' { if let Some(n) = chars.next() { out.push(n); } }
+                else if ch == '"' { in_str = false; }
+            } else if ch == ' ' || ch == '\t' {
+                if !out.ends_with(' ') { out.push(' '); }
+            } else {
+                if ch == '"' { in_str = true; }
+                out.push(ch);
+            }
+        }
+        out
+    }
+
+    /// E0003: quote the raw text between `[` and `]`.
+    fn quote_label_fix(&self, lb: &Token, rb: &Token) -> Fix {
+        let raw = self.src.get(lb.offset + lb.len..rb.offset).unwrap_or("").trim();
+        let quoted = format!("\"{}\"", raw.replace('\\', "\\\\").replace('"', "\\\""));
+        Fix{ message: format!("quote the label: {}", quoted), edits: vec![TextEdit{ span: Span{ start: Self::end_of(lb), end: Self::start_of(rb) }, replacement: quoted }] }
+    }
+
+    /// E0100: rename every use of `id`, unless the new name is a keyword or already names a
+    /// node or subgraph, in which case renaming would change the document's structure.
+    fn snake_case_fix(&self, id: &str) -> Option<Fix> {
+        let snake = Self::to_snake_case(id)?;
+        if RESERVED.contains(&snake.as_str()) || self.toks.iter().any(|t| matches!(&t.kind, TokenKind::Identifier(x) if *x == snake)) { return None; }
+        let edits = self.toks.iter().filter(|t| matches!(&t.kind, TokenKind::Identifier(x) if x == id)).map(|t| TextEdit{ span: Self::tok_span(t), replacement: snake.clone() }).collect();
+        Some(Fix{ message: format!("rename '{}' to '{}'", id, snake), edits })
+    }
+
+    /// E0300: collapse redundant whitespace when that is enough to fit the limit, otherwise
+    /// continue the label of a node declaration on following lines.
+    fn wrap_line_fix(&self, line: usize) -> Option<Fix> {
+        let text = self.line_text(line).trim_end_matches('\r');
+        let collapsed = Self::collapse_ws(text);
+        if collapsed.chars().count() <= 100 && collapsed != text {
+            return Some(Fix{ message: "collapse redundant whitespace".into(), edits: vec![TextEdit{ span: self.line_span(line), replacement: collapsed }] });
+        }
+        let &(_, start, end) = self.marks.iter().find(|m| m.0 == NodeKind::NodeDecl && self.toks[m.1].line == line && self.toks[m.2 - 1].line == line)?;
+        let decl = &self.toks[start..end];
+        let shape = Shape::opened_by(decl.get(1)?.kind.delimiter()?, decl.get(2).and_then(|t| t.kind.delimiter()))?;
+        let (open, close) = shape.delimiters();
+        let (TokenKind::Identifier(id), Some(TokenKind::StringLit(_))) = (&decl[0].kind, decl.get(1 + open.len()).map(|t| &t.kind)) else { return None };
+        let label: String = decl.iter().filter_map(|t| match &t.kind { TokenKind::StringLit(s) => Some(s.as_str()), _ => None }).collect();
+        let indent = &text[..text.len() - text.trim_start().len()];
+        let wrapped = fmt::wrap_label(&format!("{}{}", id, open), &label, close, indent);
+        Some(Fix{ message: "continue the label on the next line".into(), edits: vec![TextEdit{ span: Self::make_span(&decl[0], &decl[decl.len() - 1]), replacement: wrapped }] })
+    }
+
+    /// E0300 for every non-comment line longer than 100 characters.
+    fn long_lines(&self, diags: &mut Vec<Diagnostic>) {
+        for (i, text) in self.src.split('\n').enumerate() {
+            let text = text.trim_end_matches('\r');
+            let len = text.chars().count();
+            if len <= 100 || text.trim_start().starts_with("%%") { continue; }
+            let line = i + 1;
+            diags.push(Diagnostic{ severity: Severity::Error, code: "E0300".into(), message: format!("Line {} exceeds max length ({} > 100)", line, len), span: Some(self.line_span(line)), fixes: self.wrap_line_fix(line).into_iter().collect(), name: None, related: Vec::new() });
+        }
+    }
+
+    /// E0100 for a node or subgraph id `t` that is not snake_case.
+    fn check_snake_case(&self, t: &Token, id: &str, diags: &mut Vec<Diagnostic>) {
+        if Self::is_snake_case(id) { return; }
+        let fixes = self.snake_case_fix(id).into_iter().collect();
+        diags.push(Diagnostic{ fixes, ..Self::error("E0100", "Identifier must be snake_case".into(), Self::tok_span(t)) });
     }
 
     fn is_snake_case(id: &str) -> bool {
@@ -31,101 +240,220 @@ impl Parser {
         true
     }
 
-    fn document(&mut self) -> Result<Document, Vec<Diagnostic>> {
-        let mut diags = Vec::new();
-        let mut metadata = None;
-        let mut direction = None;
-        let mut nodes: Vec<Node> = Vec::new();
-        let mut edges: Vec<Edge> = Vec::new();
-        let mut line_start_idx = 0usize;
-        let mut line_start_col = 1usize;
-        while let TokenKind::Comment(text) = &self.peek().kind {
-            if text.trim_start().starts_with("Diagram:") { let title = text.trim_start().trim_start_matches("Diagram:").trim().to_string(); metadata = Some(Metadata{ title: Some(title), tags: None }); }
-            self.bump(); self.expect_newline();
-        }
-        match (&self.peek().kind, &self.toks.get(self.idx+1).map(|t| &t.kind)) {
-            (TokenKind::DirectionKw, Some(TokenKind::Identifier(dir))) => {
-                let d = match dir.as_str() { "LR" => Direction::LR, "TD" => Direction::TD, _ => { diags.push(Diagnostic{ code:"E0001".into(), message:"Invalid direction".into(), span: Some(Span{ start: Position{ line: self.peek().line, col: self.peek().col }, end: Position{ line: self.peek().line, col: self.peek().col+1 } })}); Direction::LR } };
-                direction = Some(d);
-                self.bump(); self.bump(); self.expect_newline();
+    fn error(code: &str, message: String, span: Span) -> Diagnostic {
+        Diagnostic{ severity: Severity::Error, code: code.into(), message, span: Some(span), fixes: Vec::new(), name: None, related: Vec::new() }
+    }
+
+    /// How a token is named in "expected X, found Y" messages.
+    fn describe(kind: &TokenKind) -> String {
+        match kind {
+            TokenKind::DirectionKw => "'direction'".into(),
+            TokenKind::Identifier(id) => format!("identifier '{}'", id),
+            TokenKind::StringLit(_) => "a string".into(),
+            TokenKind::ArrowFlow => "'-->'".into(),
+            TokenKind::ArrowCond => "'-.->'".into(),
+            TokenKind::LBracket | TokenKind::RBracket | TokenKind::LBrace | TokenKind::RBrace | TokenKind::LParen | TokenKind::RParen => format!("'{}'", kind.delimiter().unwrap_or_default()),
+            TokenKind::Pipe => "'|'".into(),
+            TokenKind::LabelText(_) => "label text".into(),
+            TokenKind::Comment(_) => "a comment".into(),
+            TokenKind::Newline => "end of line".into(),
+            TokenKind::Eof => "end of input".into(),
+        }
+    }
+
+    /// Records the tokens from `start` up to the current one as a `kind` node.
+    fn mark(&mut self, kind: NodeKind, start: usize) { if self.idx > start { self.marks.push((kind, start, self.idx)); } }
+
+    fn at_line_end(&self) -> bool { matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof | TokenKind::Comment(_)) }
+
+    /// Skips to the end of the current line, where the next statement starts.
+    fn synchronize(&mut self) { while !self.at_line_end() { self.bump(); } }
+
+    /// E0011 "expected X, found Y" at the current token, then synchronize. Lines the lexer
+    /// already reported are not reported again, as the parser only sees what survived lexing.
+    fn unexpected(&mut self, expected: &str, diags: &mut Vec<Diagnostic>) {
+        let t = self.peek();
+        if !self.lex_diags.iter().any(|d| d.span.as_ref().is_some_and(|s| s.start.line == t.line)) {
+            diags.push(Self::error("E0011", format!("Expected {}, found {}", expected, Self::describe(&t.kind)), Self::tok_span(t)));
+        }
+        let from = self.marks.last().map_or(0, |m| m.2).max(self.stmt_start);
+        self.synchronize();
+        self.mark(NodeKind::Error, from);
+    }
+
+    /// statement-end := NEWLINE | EOF | COMMENT. Another statement on the same line is E0311
+    /// and parsing resumes at it.
+    fn end_statement(&mut self, diags: &mut Vec<Diagnostic>) {
+        if matches!(self.peek().kind, TokenKind::Identifier(_)) { let text = self.line_text(self.peek().line); let indent = &text[..text.len() - text.trim_start().len()]; diags.push(style::second_statement(self.peek(), indent)); return; }
+        if !self.at_line_end() { self.unexpected("end of line", diags); }
+    }
+
+    /// header := (COMMENT NEWLINE | NEWLINE)*, reading `%% Diagram:` and `%% Meta:` lines.
+    fn header(&mut self, diags: &mut Vec<Diagnostic>) {
+        loop {
+            match &self.peek().kind {
+                TokenKind::Newline => { self.bump(); }
+                TokenKind::Comment(_) => { self.bump(); self.expect_newline(); }
+                _ => break,
             }
-            _ => { diags.push(Diagnostic{ code:"E0001".into(), message:"Missing direction on first line".into(), span: Some(Span{ start: Position{ line: self.peek().line, col: self.peek().col }, end: Position{ line: self.peek().line, col: self.peek().col } })}); }
         }
+        let meta = Self::metadata(&self.toks[..self.idx], diags);
+        let has_title = meta.and_then(|m| m.title).is_some_and(|t| !t.is_empty());
+        if !has_title && !self.opts.allow_missing_title {
+            diags.push(Self::error("E0002", "Missing required '%% Diagram: <title>' header".into(), self.line_span(1)));
+        }
+        self.mark(NodeKind::Header, 0);
+    }
+
+    /// directive := 'direction' ('LR' | 'RL' | 'TD' | 'TB' | 'BT') statement-end
+    fn directive(&mut self, diags: &mut Vec<Diagnostic>) {
+        if !matches!(self.peek().kind, TokenKind::DirectionKw) {
+            diags.push(Self::error("E0001", "Missing direction on first line".into(), Self::tok_span(self.peek())));
+            return;
+        }
+        let start = self.idx;
+        self.stmt_start = start;
+        self.bump();
+        if !matches!(&self.peek().kind, TokenKind::Identifier(dir) if Direction::from_keyword(dir).is_some()) {
+            diags.push(Self::error("E0001", format!("Invalid direction: expected LR, RL, TD, TB or BT, found {}", Self::describe(&self.peek().kind)), Self::tok_span(self.peek())));
+            self.synchronize();
+            self.mark(NodeKind::Directive, start);
+            return;
+        }
+        self.bump();
+        self.mark(NodeKind::Directive, start);
+        self.end_statement(diags);
+    }
+
+    /// node-decl := IDENT open label close statement-end, with open/close one of the
+    /// [`Shape::delimiters`] pairs: `[` `]`, `{` `}`, `[(` `)]` or `([` `])`
+    fn node_decl(&mut self, diags: &mut Vec<Diagnostic>) {
+        let (start_idx, start_tok) = (self.idx, self.peek().clone());
+        let TokenKind::Identifier(nid) = start_tok.kind.clone() else { unreachable!("node_decl starts at an identifier") };
+        self.bump();
+        let Some(shape) = self.delim(self.idx).and_then(|c| Shape::opened_by(c, self.delim(self.idx + 1))) else { return self.unexpected("'['", diags); };
+        let (open, close) = shape.delimiters();
+        for _ in open.chars() { self.bump(); }
+        match &self.peek().kind { TokenKind::StringLit(_) => { self.bump(); self.label_rest(); }, _ => {
+            let lb = self.toks[self.idx-1].clone();
+            let rb = self.toks[self.idx..].iter().take_while(|t| !matches!(t.kind, TokenKind::Newline | TokenKind::Eof)).position(|t| t.kind.delimiter() == close.chars().next()).map(|off| self.idx + off);
+            let fixes = rb.map(|i| self.quote_label_fix(&lb, &self.toks[i])).into_iter().collect();
+            let span = match rb { Some(i) if i > self.idx => Self::make_span(self.peek(), &self.toks[i-1]), _ => Span{ start: Self::end_of(&lb), end: Self::end_of(&lb) } };
+            diags.push(Diagnostic{ fixes, ..Self::error("E0003", "Node label must be quoted".into(), span) });
+            // unquoted label text is reported as a whole, not character by character
+            if let Some(i) = rb { let (a, b) = (lb.offset, self.toks[i].offset); self.lex_diags.retain(|d| !d.span.as_ref().is_some_and(|s| s.start.offset > a && s.end.offset <= b)); self.idx = i; }
+        } }
+        let closed = close.chars().enumerate().all(|(i, c)| self.delim(self.idx + i) == Some(c));
+        if closed { for _ in close.chars() { self.bump(); } } else { diags.push(Self::error("E0010", format!("Expected {} after label, found {}", close, Self::describe(&self.peek().kind)), Self::tok_span(self.peek()))); }
+        self.check_snake_case(&start_tok, &nid, diags);
+        self.mark(NodeKind::NodeDecl, start_idx);
+        if closed { self.end_statement(diags); } else { let rest = self.idx; self.synchronize(); self.mark(NodeKind::Error, rest); }
+    }
+
+    /// edge := IDENT ('-->' | '-.->') ('|' (LABEL | STRING) '|')? IDENT statement-end
+    fn edge(&mut self, diags: &mut Vec<Diagnostic>) {
+        let start_idx = self.idx;
+        self.bump();
+        let arrow = match self.peek().kind { TokenKind::ArrowCond => "'-.->'", _ => "'-->'" };
+        self.bump();
+        if matches!(self.peek().kind, TokenKind::Pipe) && !self.edge_label() { self.bump(); if !self.at_line_end() { self.bump(); } return self.unexpected("'|' after edge label", diags); }
+        if !matches!(self.peek().kind, TokenKind::Identifier(_)) { return self.unexpected(&format!("a node id after {}", arrow), diags); }
+        self.bump();
+        self.mark(NodeKind::Edge, start_idx);
+        diags.extend(style::arrow_spacing(&self.toks[start_idx..self.idx]));
+        self.end_statement(diags);
+    }
+
+    /// Whether the lexer skipped text between the identifier here and the next one on its
+    /// line, as it does for the arrow of `a -> b`.
+    fn arrow_lost(&self) -> bool {
+        let (a, b) = (&self.toks[self.idx], &self.toks[self.idx + 1]);
+        a.line == b.line && self.lex_diags.iter().any(|d| d.span.as_ref().is_some_and(|s| s.start.offset >= a.offset + a.len && s.end.offset <= b.offset))
+    }
+
+    /// IDENT IDENT statement-end around an arrow the lexer could not read. The edge is kept, so
+    /// the lexer error is the only one reported for the line and its nodes are not orphans.
+    fn partial_edge(&mut self, diags: &mut Vec<Diagnostic>) {
+        let start_idx = self.idx;
+        self.bump(); self.bump();
+        self.mark(NodeKind::Edge, start_idx);
+        self.end_statement(diags);
+    }
+
+    /// subgraph := 'subgraph' IDENT '[' label ']' statement-end, opening a block that runs to
+    /// the matching `end`. A malformed header still opens the block, so its `end` matches.
+    fn subgraph(&mut self, diags: &mut Vec<Diagnostic>) {
+        let start = self.idx;
+        self.open.push(start);
+        self.bump();
+        let id_tok = self.peek().clone();
+        let TokenKind::Identifier(gid) = &id_tok.kind else { unreachable!("subgraph is followed by an identifier") };
+        self.bump();
+        if !matches!(self.peek().kind, TokenKind::LBracket) { return self.unexpected(&format!("'[' and a title after '{}'", gid), diags); }
+        self.bump();
+        if !matches!(self.peek().kind, TokenKind::StringLit(_)) { return self.unexpected("a quoted title", diags); }
+        self.bump(); self.label_rest();
+        if !matches!(self.peek().kind, TokenKind::RBracket) { return self.unexpected("']' after the title", diags); }
+        self.bump();
+        self.check_snake_case(&id_tok, gid, diags);
+        self.mark(NodeKind::Subgraph, start);
+        self.end_statement(diags);
+    }
+
+    /// member := IDENT statement-end, inside a subgraph: adds a node declared elsewhere.
+    fn member(&mut self, diags: &mut Vec<Diagnostic>) {
+        let start = self.idx;
+        self.bump();
+        self.mark(NodeKind::Member, start);
+        self.end_statement(diags);
+    }
+
+    /// end := 'end' statement-end, closing the innermost subgraph.
+    fn end(&mut self, diags: &mut Vec<Diagnostic>) {
+        let start = self.idx;
+        if self.open.pop().is_none() {
+            diags.push(Self::error("E0011", "Found 'end' with no open subgraph".into(), Self::tok_span(self.peek())));
+            self.bump();
+            return self.mark(NodeKind::Error, start);
+        }
+        self.bump();
+        self.mark(NodeKind::End, start);
+        self.end_statement(diags);
+    }
+
+    /// document := header directive statement*, recorded in `marks` for [`cst::build`]
+    /// statement := node-decl | edge | subgraph | member | end | COMMENT | NEWLINE
+    fn document(&mut self) -> Vec<Diagnostic> {
+        let mut diags = Vec::new();
+        self.header(&mut diags);
+        self.directive(&mut diags);
         while !matches!(self.peek().kind, TokenKind::Eof) {
-            if matches!(self.peek().kind, TokenKind::Newline) {
-                if self.idx > line_start_idx {
-                    let start_tok = &self.toks[line_start_idx];
-                    let end_tok = &self.toks[self.idx - 1];
-                    let len = end_tok.col - line_start_col;
-                    if len > 100 { diags.push(Diagnostic{ code: "E0300".into(), message: format!("Line {} exceeds max length ({} > 100)", start_tok.line, len), span: Some(Span{ start: Position{ line: start_tok.line, col: line_start_col }, end: Position{ line: end_tok.line, col: end_tok.col } })}); }
-                }
-                self.bump(); line_start_idx = self.idx; line_start_col = 1; continue;
-            }
-            if matches!(self.peek().kind, TokenKind::Comment(_)) { self.bump(); self.expect_newline(); continue; }
-            if self.toks.get(line_start_idx).map(|t| t.line).unwrap_or(0) != self.peek().line { line_start_idx = self.idx; line_start_col = self.peek().col; }
+            self.stmt_start = self.idx;
             match &self.peek().kind {
-                TokenKind::Identifier(id) => {
-                    let nid = id.clone();
-                    if matches!(self.toks.get(self.idx+1).map(|t| &t.kind), Some(TokenKind::LBracket)) {
-                        let start_tok = self.peek().clone();
-                        self.bump(); self.bump();
-                        let label = match &self.peek().kind { TokenKind::StringLit(s) => { let x=s.clone(); self.bump(); x }, _ => { diags.push(Diagnostic{ code:"E0003".into(), message:"Node label must be quoted".into(), span: Some(Span{ start: Position{ line: self.peek().line, col: self.peek().col }, end: Position{ line: self.peek().line, col: self.peek().col } })}); String::new() } };
-                        match self.peek().kind { TokenKind::RBracket => { self.bump(); }, _ => { diags.push(Diagnostic{ code:"E0010".into(), message:"Expected ] after label".into(), span: Some(Span{ start: Position{ line: self.peek().line, col: self.peek().col }, end: Position{ line: self.peek().line, col: self.peek().col } })}); } }
-                        if !Self::is_snake_case(&nid) { diags.push(Diagnostic{ code:"E0100".into(), message:"Identifier must be snake_case".into(), span: Some(Self::make_span(&start_tok, &start_tok)) }); }
-                        nodes.push(Node{ id: nid, label, span: Some(Self::make_span(&start_tok, &self.toks[self.idx-1])) });
-                    } else if let Some(arrow_tok) = self.toks.get(self.idx+1) {
-                        let kind = match arrow_tok.kind { TokenKind::ArrowFlow => Some(EdgeKind::Flow), TokenKind::ArrowCond => Some(EdgeKind::Conditional), _ => None };
-                        if let Some(k) = kind {
-                            if let Some(Token{kind: TokenKind::Identifier(dst_id), ..}) = self.toks.get(self.idx+2) {
-                                let from = nid; let to = dst_id.clone(); let start_tok = self.peek().clone(); let end_tok = self.toks[self.idx+2].clone();
-                                self.bump(); self.bump(); self.bump();
-                                edges.push(Edge{ from, to, kind: k, span: Some(Self::make_span(&start_tok, &end_tok)) });
-                                while !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof) { self.bump(); }
-                            } else { while !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof) { self.bump(); } }
-                        } else { while !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof) { self.bump(); } }
-                    } else { while !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof) { self.bump(); } }
-                }
                 TokenKind::Newline => { self.bump(); }
-                TokenKind::Comment(_) => { self.bump(); }
-                TokenKind::ArrowFlow | TokenKind::ArrowCond => { while !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof) { self.bump(); } }
-                _ => {
-                    if let TokenKind::Identifier(src_id) = &self.peek().kind {
-                        if let Some(arrow_tok) = self.toks.get(self.idx+1) {
-                            let kind = match arrow_tok.kind { TokenKind::ArrowFlow => Some(EdgeKind::Flow), TokenKind::ArrowCond => Some(EdgeKind::Conditional), _ => None };
-                            if let Some(k) = kind {
-                                if let Some(Token{kind: TokenKind::Identifier(dst_id), ..}) = self.toks.get(self.idx+2) {
-                                    let from = src_id.clone(); let to = dst_id.clone(); let start_tok = self.peek().clone(); let end_tok = self.toks[self.idx+2].clone();
-                                    self.bump(); self.bump(); self.bump();
-                                    edges.push(Edge{ from, to, kind: k, span: Some(Self::make_span(&start_tok, &end_tok)) });
-                                    while !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof) { self.bump(); }
-                                    continue;
-                                }
-                            }
-                        }
-                    }
-                    self.bump();
+                TokenKind::Comment(text) => {
+                    if text.trim_start().starts_with("Meta:") { diags.push(Self::error("E0022", "Meta header is only allowed in the leading comment block".into(), Self::tok_span(self.peek()))); }
+                    self.bump(); self.expect_newline();
                 }
+                TokenKind::Identifier(id) => match self.toks.get(self.idx+1).map(|t| &t.kind) {
+                    Some(TokenKind::LBracket | TokenKind::LBrace | TokenKind::LParen) => self.node_decl(&mut diags),
+                    Some(TokenKind::ArrowFlow | TokenKind::ArrowCond) => self.edge(&mut diags),
+                    Some(TokenKind::Identifier(_)) if id == "subgraph" => self.subgraph(&mut diags),
+                    Some(TokenKind::Identifier(_)) if self.arrow_lost() => self.partial_edge(&mut diags),
+                    Some(TokenKind::Newline | TokenKind::Eof | TokenKind::Comment(_)) if id == "end" => self.end(&mut diags),
+                    Some(TokenKind::Newline | TokenKind::Eof | TokenKind::Comment(_)) if !self.open.is_empty() => self.member(&mut diags),
+                    _ => { let expected = format!("'[' or an arrow after '{}'", id); self.bump(); self.unexpected(&expected, &mut diags); }
+                },
+                _ => self.unexpected("a node declaration or edge", &mut diags),
             }
         }
-        if !diags.is_empty() { return Err(diags); }
-        let doc = Document{ version: "1".into(), directives: Directives{ direction: direction.unwrap_or(Direction::LR) }, metadata, nodes, edges };
-        let mut errors = Vec::new();
-        let mut ids = std::collections::BTreeSet::new();
-        for n in &doc.nodes { ids.insert(n.id.clone()); }
-        for e in &doc.edges {
-            if !ids.contains(&e.from) { errors.push(Diagnostic{ code: "E0201".into(), message: format!("Edge from references unknown node '{}'", e.from), span: e.span.clone() }); }
-            if !ids.contains(&e.to) { errors.push(Diagnostic{ code: "E0202".into(), message: format!("Edge to references unknown node '{}'", e.to), span: e.span.clone() }); }
-        }
-        if !errors.is_empty() { return Err(errors); }
-        let mut degree: std::collections::BTreeMap<&str, usize> = std::collections::BTreeMap::new();
-        for n in &doc.nodes { degree.insert(&n.id, 0); }
-        for e in &doc.edges {
-            if let Some(d) = degree.get_mut(e.from.as_str()) { *d += 1; }
-            if let Some(d) = degree.get_mut(e.to.as_str()) { *d += 1; }
-        }
-        let mut orphan_errors = Vec::new();
-        for n in &doc.nodes { let deg = degree.get(n.id.as_str()).copied().unwrap_or(0); if deg == 0 { orphan_errors.push(Diagnostic{ code: "E0203".into(), message: format!("Orphan node '{}' has no edges", n.id), span: n.span.clone() }); } }
-        if !orphan_errors.is_empty() { return Err(orphan_errors); }
-        Ok(doc)
+        for &start in &self.open {
+            let (kw, id) = (&self.toks[start], &self.toks[start + 1]);
+            let TokenKind::Identifier(gid) = &id.kind else { continue };
+            diags.push(Self::error("E0012", format!("Subgraph '{}' is missing 'end'", gid), Self::make_span(kw, id)));
+        }
+        self.long_lines(&mut diags);
+        diags.append(&mut self.lex_diags);
+        diags
     }
 }
@@ -0,0 +1,123 @@
+//! Layout rules checked by the parser. [`crate::fmt`] writes edges with [`edge`], so its
+//! output has the spacing E0310 checks, and puts every statement on a line of its own, as
+//! E0311 requires. The fixes, applied by `graphrite fix`, leave everything else as written.
+use crate::ast::{Position, Span};
+use crate::error::{Diagnostic, Fix, Severity, TextEdit};
+use crate::lexer::{Token, TokenKind};
+
+/// Whitespace before an edge's arrow and before its destination.
+const AROUND_ARROW: &str = " ";
+/// Whitespace between an arrow and the `|` opening its label.
+const BEFORE_LABEL: &str = "";
+
+/// Source text of an arrow token.
+pub(crate) fn arrow_text(kind: &TokenKind) -> &'static str {
+    if matches!(kind, TokenKind::ArrowCond) {
+        "-.->"
+    } else {
+        "-->"
+    }
+}
+
+/// The edge `from arrow |label| to`, spaced as E0310 expects. `label` is the source text
+/// between the pipes.
+pub(crate) fn edge(from: &str, arrow: &str, label: Option<&str>, to: &str) -> String {
+    let label = label.map_or(String::new(), |l| format!("{}|{}|", BEFORE_LABEL, l));
+    format!(
+        "{}{}{}{}{}{}",
+        from, AROUND_ARROW, arrow, label, AROUND_ARROW, to
+    )
+}
+
+/// Span of the whitespace directly before `t`.
+fn ws_span(t: &Token) -> Span {
+    let n = t.ws_before.len();
+    Span {
+        start: Position {
+            line: t.line,
+            col: t.col - n,
+            col_utf16: t.col_utf16 - n,
+            offset: t.offset - n,
+        },
+        end: Position {
+            line: t.line,
+            col: t.col,
+            col_utf16: t.col_utf16,
+            offset: t.offset,
+        },
+    }
+}
+
+fn layout_error(code: &str, message: String, at: &Token, fix: Fix) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        code: code.into(),
+        message,
+        span: Some(Span {
+            start: ws_span(at).end,
+            end: Position {
+                line: at.line,
+                col: at.col + at.len,
+                col_utf16: at.col_utf16 + at.len_utf16,
+                offset: at.offset + at.len,
+            },
+        }),
+        fixes: vec![fix],
+        name: None,
+        related: Vec::new(),
+    }
+}
+
+/// E0310: `edge` holds the tokens of one edge statement, `src arrow [| label |] dst`,
+/// which must read `a --> b` or `a -->|label| b`.
+pub fn arrow_spacing(edge: &[Token]) -> Option<Diagnostic> {
+    let [_, arrow, after, ..] = edge else {
+        return None;
+    };
+    let dst = edge.last()?;
+    let mut expected = vec![(arrow, AROUND_ARROW)];
+    if matches!(after.kind, TokenKind::Pipe) {
+        expected.push((after, BEFORE_LABEL));
+    }
+    expected.push((dst, AROUND_ARROW));
+    let edits: Vec<TextEdit> = expected
+        .into_iter()
+        .filter(|(t, ws)| t.ws_before != *ws)
+        .map(|(t, ws)| TextEdit {
+            span: ws_span(t),
+            replacement: ws.to_string(),
+        })
+        .collect();
+    if edits.is_empty() {
+        return None;
+    }
+    Some(layout_error(
+        "E0310",
+        format!(
+            "Expected a single space on each side of '{}'",
+            arrow_text(&arrow.kind)
+        ),
+        arrow,
+        Fix {
+            message: "normalize arrow spacing".into(),
+            edits,
+        },
+    ))
+}
+
+/// E0311: `next` starts a second statement on the line of the previous one, which is
+/// indented by `indent`; the fix moves it to a line of its own at the same indentation.
+pub fn second_statement(next: &Token, indent: &str) -> Diagnostic {
+    layout_error(
+        "E0311",
+        "Only one statement is allowed per line".into(),
+        next,
+        Fix {
+            message: "move the statement to its own line".into(),
+            edits: vec![TextEdit {
+                span: ws_span(next),
+                replacement: format!("\n{}", indent),
+            }],
+        },
+    )
+}
@@ -0,0 +1,332 @@
+use crate::ast::{Document, Group, Node, NodeRef, Span};
+use crate::error::{Diagnostic, Related, Severity};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A semantic check run over a parsed [`Document`].
+pub trait Rule {
+    /// Diagnostic code emitted by this rule, e.g. `E0203`.
+    fn id(&self) -> &'static str;
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+    fn check(&self, doc: &Document) -> Vec<Diagnostic>;
+
+    /// A diagnostic with this rule's code at its default severity.
+    fn diagnostic(&self, message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            severity: self.default_severity(),
+            ..Diagnostic::new(self.id(), message, span)
+        }
+    }
+}
+
+/// Ordered set of rules applied after parsing.
+pub struct Registry {
+    rules: Vec<Box<dyn Rule>>,
+    severities: BTreeMap<&'static str, Severity>,
+}
+
+impl Registry {
+    /// A registry with no rules.
+    pub fn empty() -> Self {
+        Registry {
+            rules: Vec::new(),
+            severities: BTreeMap::new(),
+        }
+    }
+
+    /// Overrides the severity of every diagnostic produced by rule `id`.
+    pub fn set_severity(&mut self, id: &'static str, severity: Severity) -> &mut Self {
+        self.severities.insert(id, severity);
+        self
+    }
+
+    pub fn register<R: Rule + 'static>(&mut self, rule: R) -> &mut Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|r| r.as_ref())
+    }
+
+    pub fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let mut out = Vec::new();
+        for rule in &self.rules {
+            let mut diags = rule.check(doc);
+            if let Some(sev) = self.severities.get(rule.id()) {
+                diags.iter_mut().for_each(|d| d.severity = *sev);
+            }
+            out.extend(diags);
+        }
+        out
+    }
+}
+
+impl Default for Registry {
+    /// The built-in reference, orphan and style checks.
+    fn default() -> Self {
+        let mut reg = Registry::empty();
+        reg.register(EdgeFromUnknown)
+            .register(EdgeToUnknown)
+            .register(OrphanNode)
+            .register(DuplicateNode)
+            .register(UnknownGroupMember)
+            .register(NodeInMultipleGroups)
+            .register(DuplicateGroupId)
+            .register(LongLabel);
+        reg
+    }
+}
+
+fn node_ids(doc: &Document) -> BTreeSet<&str> {
+    doc.nodes.iter().map(|n| n.id.as_str()).collect()
+}
+
+/// E0201: edge source is not a declared node.
+pub struct EdgeFromUnknown;
+
+impl Rule for EdgeFromUnknown {
+    fn id(&self) -> &'static str {
+        "E0201"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let ids = node_ids(doc);
+        doc.edges
+            .iter()
+            .filter(|e| !ids.contains(e.from.as_str()))
+            .map(|e| {
+                self.diagnostic(
+                    format!("Edge from references unknown node '{}'", e.from),
+                    e.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// E0202: edge destination is not a declared node.
+pub struct EdgeToUnknown;
+
+impl Rule for EdgeToUnknown {
+    fn id(&self) -> &'static str {
+        "E0202"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let ids = node_ids(doc);
+        doc.edges
+            .iter()
+            .filter(|e| !ids.contains(e.to.as_str()))
+            .map(|e| {
+                self.diagnostic(
+                    format!("Edge to references unknown node '{}'", e.to),
+                    e.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// E0203: node has no incident edges.
+pub struct OrphanNode;
+
+impl Rule for OrphanNode {
+    fn id(&self) -> &'static str {
+        "E0203"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let mut degree: BTreeMap<&str, usize> = BTreeMap::new();
+        for n in &doc.nodes {
+            degree.insert(&n.id, 0);
+        }
+        for e in &doc.edges {
+            if let Some(d) = degree.get_mut(e.from.as_str()) {
+                *d += 1;
+            }
+            if let Some(d) = degree.get_mut(e.to.as_str()) {
+                *d += 1;
+            }
+        }
+        doc.nodes
+            .iter()
+            .filter(|n| degree.get(n.id.as_str()).copied().unwrap_or(0) == 0)
+            .map(|n| {
+                self.diagnostic(
+                    format!("Orphan node '{}' has no edges", n.id),
+                    n.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// E0204: node id declared more than once.
+pub struct DuplicateNode;
+
+impl Rule for DuplicateNode {
+    fn id(&self) -> &'static str {
+        "E0204"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let mut first: BTreeMap<&str, &Node> = BTreeMap::new();
+        let mut out = Vec::new();
+        for n in &doc.nodes {
+            let Some(prev) = first.get(n.id.as_str()) else {
+                first.insert(&n.id, n);
+                continue;
+            };
+            out.push(Diagnostic {
+                related: related("first declared here".into(), &prev.span),
+                ..self.diagnostic(
+                    format!("Node '{}' is declared more than once", n.id),
+                    n.span.clone(),
+                )
+            });
+        }
+        out
+    }
+}
+
+/// Every group in `doc`, parents before their children.
+fn groups(doc: &Document) -> Vec<&Group> {
+    fn walk<'a>(groups: &'a [Group], out: &mut Vec<&'a Group>) {
+        for g in groups {
+            out.push(g);
+            walk(&g.groups, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(&doc.groups, &mut out);
+    out
+}
+
+fn related(message: String, span: &Option<Span>) -> Vec<Related> {
+    span.clone()
+        .map(|span| Related { message, span })
+        .into_iter()
+        .collect()
+}
+
+/// E0205: subgraph lists a node that is not declared.
+pub struct UnknownGroupMember;
+
+impl Rule for UnknownGroupMember {
+    fn id(&self) -> &'static str {
+        "E0205"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let ids = node_ids(doc);
+        groups(doc)
+            .into_iter()
+            .flat_map(|g| g.nodes.iter().map(move |r| (g, r)))
+            .filter(|(_, r)| !ids.contains(r.id.as_str()))
+            .map(|(g, r)| {
+                self.diagnostic(
+                    format!("Subgraph '{}' lists unknown node '{}'", g.id, r.id),
+                    r.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// E0206: node listed in more than one subgraph.
+pub struct NodeInMultipleGroups;
+
+impl Rule for NodeInMultipleGroups {
+    fn id(&self) -> &'static str {
+        "E0206"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let mut first: BTreeMap<&str, (&Group, &NodeRef)> = BTreeMap::new();
+        let mut out = Vec::new();
+        for g in groups(doc) {
+            for r in &g.nodes {
+                let Some((prev, prev_ref)) = first.get(r.id.as_str()) else {
+                    first.insert(&r.id, (g, r));
+                    continue;
+                };
+                out.push(Diagnostic {
+                    related: related(format!("added to '{}' here", prev.id), &prev_ref.span),
+                    ..self.diagnostic(
+                        format!("Node '{}' is already in subgraph '{}'", r.id, prev.id),
+                        r.span.clone(),
+                    )
+                });
+            }
+        }
+        out
+    }
+}
+
+/// E0207: subgraph id already used by a node or an earlier subgraph.
+pub struct DuplicateGroupId;
+
+impl Rule for DuplicateGroupId {
+    fn id(&self) -> &'static str {
+        "E0207"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        let mut used: BTreeMap<&str, (&str, &Option<Span>)> = doc
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), ("node declared here", &n.span)))
+            .collect();
+        let mut out = Vec::new();
+        for g in groups(doc) {
+            let Some((what, span)) = used.get(g.id.as_str()) else {
+                used.insert(&g.id, ("subgraph declared here", &g.span));
+                continue;
+            };
+            out.push(Diagnostic {
+                related: related(what.to_string(), span),
+                ..self.diagnostic(
+                    format!("Subgraph id '{}' is already used", g.id),
+                    g.span.clone(),
+                )
+            });
+        }
+        out
+    }
+}
+
+/// Labels longer than this are flagged by [`LongLabel`].
+pub const MAX_LABEL_CHARS: usize = 50;
+
+/// W0301: node label is long enough to crowd rendered output.
+pub struct LongLabel;
+
+impl Rule for LongLabel {
+    fn id(&self) -> &'static str {
+        "W0301"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        doc.nodes
+            .iter()
+            .filter(|n| n.label.chars().count() > MAX_LABEL_CHARS)
+            .map(|n| {
+                self.diagnostic(
+                    format!(
+                        "Label of '{}' is {} characters (> {})",
+                        n.id,
+                        n.label.chars().count(),
+                        MAX_LABEL_CHARS
+                    ),
+                    n.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
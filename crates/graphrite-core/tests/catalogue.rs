@@ -0,0 +1,71 @@
+mod common;
+
+use common::{analyze, codes, run_cli};
+use graphrite_core::error::ErrorCode;
+use graphrite_core::parser::Parser;
+
+#[test]
+fn examples_match_their_code() {
+    for c in ErrorCode::ALL {
+        let (_, bad) = analyze(c.bad_example());
+        let bad = codes(&bad);
+        assert!(bad.contains(&c.code()), "{}: bad example gave {:?}", c, bad);
+        let (_, good) = analyze(c.good_example());
+        assert!(
+            good.is_empty(),
+            "{}: good example gave {:?}",
+            c,
+            codes(&good)
+        );
+    }
+}
+
+#[test]
+fn lookup_by_code_or_name() {
+    assert_eq!(ErrorCode::from_code("E0203"), Some(ErrorCode::OrphanNode));
+    assert_eq!(ErrorCode::from_code("e0203"), Some(ErrorCode::OrphanNode));
+    assert_eq!(
+        ErrorCode::from_code("OrphanNode"),
+        Some(ErrorCode::OrphanNode)
+    );
+    assert_eq!(ErrorCode::from_code("E9999"), None);
+}
+
+#[test]
+fn codes_are_documented() {
+    let doc = include_str!("../../../ERROR_CODES.md");
+    for c in ErrorCode::ALL {
+        assert!(doc.contains(&format!("{} {}", c.code(), c.name())), "{}", c);
+    }
+}
+
+#[test]
+fn codes_are_listed_in_order() {
+    let doc = include_str!("../../../ERROR_CODES.md");
+    for w in ErrorCode::ALL.windows(2) {
+        assert!(w[0].code() < w[1].code(), "{} before {}", w[0], w[1]);
+        let at = |c: &ErrorCode| doc.find(&format!("\n{} ", c.code())).unwrap();
+        assert!(at(&w[0]) < at(&w[1]), "{} documented after {}", w[0], w[1]);
+    }
+}
+
+#[test]
+fn diagnostics_carry_symbolic_name() {
+    let errs =
+        Parser::parse("%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na --> a\n").unwrap_err();
+    assert_eq!(errs[0].name.as_deref(), Some("OrphanNode"));
+    let json = serde_json::to_string(&errs).unwrap();
+    assert!(json.contains("\"code\":\"E0203\",\"name\":\"OrphanNode\""));
+}
+
+#[test]
+fn explain_command() {
+    let (code, out, _) = run_cli(&["explain", "E0203"]);
+    assert_eq!(code, 0);
+    assert!(out.starts_with("E0203 OrphanNode: Node has zero incident edges\n"));
+    assert!(out.contains("Bad:\n"));
+    assert!(out.contains("Good:\n"));
+    let (code, _, err) = run_cli(&["explain", "E9999"]);
+    assert_eq!(code, 1);
+    assert!(err.contains("unknown error code"));
+}
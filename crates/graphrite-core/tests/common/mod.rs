@@ -0,0 +1,35 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use graphrite_core::ast::Document;
+use graphrite_core::error::Diagnostic;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+use std::process::Command;
+
+pub fn run_cli(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new("cargo")
+        .args(["run", "-q", "-p", "graphrite-cli", "--"])
+        .args(args)
+        .output()
+        .expect("run cli");
+    (
+        output.status.code().unwrap_or(1),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+/// A diagram titled `T`, laid out left to right, with `body` as its statements.
+pub fn diagram(body: &str) -> String {
+    format!("%% Diagram: T\ndirection LR\n{}", body)
+}
+
+/// Parses `src` and runs the default rules over it.
+pub fn analyze(src: &str) -> (Document, Vec<Diagnostic>) {
+    Parser::analyze(src, &ParseOptions::default(), &Registry::default())
+}
+
+pub fn codes(diags: &[Diagnostic]) -> Vec<&str> {
+    diags.iter().map(|d| d.code.as_str()).collect()
+}
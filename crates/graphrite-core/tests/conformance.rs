@@ -0,0 +1,104 @@
+mod common;
+
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+/// Every file in `samples/invalid` with the code it exists to demonstrate.
+const EXPECTED: &[(&str, &str)] = &[
+    ("01-missing-direction.mmd", "E0001"),
+    ("02-id-not-snake.mmd", "E0100"),
+    ("03-unquoted-label.mmd", "E0003"),
+    ("04-inline-comment.mmd", "E0033"),
+    ("05-dangling-edge.mmd", "E0201"),
+    ("06-orphan-node.mmd", "E0203"),
+    ("07-long-line.mmd", "E0300"),
+    ("08-arrow-spacing.mmd", "E0310"),
+    ("09-node-in-two-subgraphs.mmd", "E0206"),
+];
+
+#[test]
+fn every_invalid_sample_fails_with_its_code() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../samples/invalid");
+    let mut seen = 0;
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let p = entry.unwrap().path();
+        if p.extension().and_then(|s| s.to_str()) != Some("mmd") {
+            continue;
+        }
+        let name = p.file_name().unwrap().to_str().unwrap();
+        let (_, code) = EXPECTED
+            .iter()
+            .find(|(f, _)| *f == name)
+            .unwrap_or_else(|| panic!("{} is not listed in EXPECTED", name));
+        let src = std::fs::read_to_string(&p).unwrap();
+        let errs = Parser::parse(&src).expect_err(name);
+        assert!(
+            errs.iter().any(|d| d.code == *code),
+            "{}: expected {}, got {:?}",
+            name,
+            code,
+            errs.iter().map(|d| &d.code).collect::<Vec<_>>()
+        );
+        seen += 1;
+    }
+    assert_eq!(seen, EXPECTED.len());
+}
+
+#[test]
+fn inline_comment_span_and_fix() {
+    let src = "%% Diagram: T\ndirection LR\n  a[\"A\"]   %% note\na --> a\n";
+    let (_, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    let d = diags.iter().find(|d| d.code == "E0033").unwrap();
+    let span = d.span.as_ref().unwrap();
+    assert_eq!(&src[span.start.offset..span.end.offset], "%% note");
+    let (fixed, n) = graphrite_core::fix::apply(src, &d.fixes);
+    assert_eq!(n, 1);
+    assert_eq!(
+        fixed,
+        "%% Diagram: T\ndirection LR\n  %% note\n  a[\"A\"]\na --> a\n"
+    );
+    assert!(Parser::parse(&fixed).is_ok());
+}
+
+fn codes(body: &str) -> Vec<String> {
+    let (_, diags) = common::analyze(&common::diagram(body));
+    diags.into_iter().map(|d| d.code).collect()
+}
+
+#[test]
+fn arrow_spacing_is_checked() {
+    let ok = "a[\"A\"]\nb[\"B\"]\na --> b\na -->|x| b\na -.->|\"y\"| b\n";
+    assert!(codes(ok).is_empty(), "{:?}", codes(ok));
+    for bad in [
+        "a-->b\n",
+        "a  --> b\n",
+        "a -.-> \tb\n",
+        "a --> |x| b\n",
+        "a -->|x|b\n",
+    ] {
+        let src = format!("a[\"A\"]\nb[\"B\"]\n{}", bad);
+        assert_eq!(codes(&src), ["E0310"], "{:?}", bad);
+    }
+}
+
+#[test]
+fn second_statement_is_reported_and_kept() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"] b[\"B\"]\nc[\"C\"]\na --> b c --> b\n";
+    let (doc, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    let codes: Vec<&str> = diags.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, ["E0311", "E0311"]);
+    assert_eq!(doc.nodes.len(), 3);
+    assert_eq!(doc.edges.len(), 2);
+    let span = diags[1].span.as_ref().unwrap();
+    assert_eq!(&src[span.start.offset..span.end.offset], "c");
+}
+
+#[test]
+fn layout_fixes_apply() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"] b[\"B\"]\na-->|x|  b\n";
+    let (_, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    assert_eq!(
+        graphrite_core::fix::apply_fixes(src, &diags).0,
+        "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -->|x| b\n"
+    );
+}
@@ -0,0 +1,96 @@
+use graphrite_core::cst::{Element, NodeKind};
+use graphrite_core::parser::Parser;
+
+fn sample_files() -> Vec<std::path::PathBuf> {
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../samples");
+    let mut files = Vec::new();
+    for dir in ["valid", "invalid"] {
+        for entry in std::fs::read_dir(root.join(dir)).unwrap() {
+            let p = entry.unwrap().path();
+            if p.extension().and_then(|s| s.to_str()) == Some("mmd") {
+                files.push(p);
+            }
+        }
+    }
+    files
+}
+
+#[test]
+fn round_trips_every_sample() {
+    let files = sample_files();
+    assert!(files.len() > 10);
+    for p in files {
+        let src = std::fs::read_to_string(&p).unwrap();
+        assert_eq!(Parser::syntax(&src).to_string(), src, "{:?}", p);
+    }
+}
+
+#[test]
+fn round_trips_odd_input() {
+    for src in [
+        "",
+        "\n\n",
+        "no newline",
+        "%% Diagram: T\r\ndirection LR\r\na[\"A\"]\r\n",
+        "a(\"x\") -> b ] | \"open\n\t1bad[x y]\n",
+        "%% Diagram: T\ndirection LR\na --> b c --> d %% trailing",
+    ] {
+        assert_eq!(Parser::syntax(src).to_string(), src);
+    }
+}
+
+#[test]
+fn document_is_derived_from_tree() {
+    for p in sample_files() {
+        let src = std::fs::read_to_string(&p).unwrap();
+        if let Ok(doc) = Parser::parse(&src) {
+            assert_eq!(Parser::syntax(&src).document(), doc, "{:?}", p);
+        }
+    }
+}
+
+#[test]
+fn statements_become_nodes() {
+    let src = "%% Diagram: T\n\ndirection LR\n  a[\"A\"]\n%% note\na -->|x| a\noops\n";
+    let root = Parser::syntax(src);
+    let kinds: Vec<NodeKind> = root.nodes().map(|n| n.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            NodeKind::Header,
+            NodeKind::Directive,
+            NodeKind::NodeDecl,
+            NodeKind::Edge,
+            NodeKind::Error
+        ]
+    );
+    let decl = root.nodes().nth(2).unwrap();
+    assert_eq!(decl.to_string(), "a[\"A\"]");
+    assert!(root
+        .children
+        .iter()
+        .any(|c| matches!(c, Element::Trivia(_, t) if t == "  ")));
+}
+
+#[test]
+fn metadata_is_read_from_the_header_node() {
+    let src = "%% Diagram: T\n%% Meta: a=1; broken; a=2\n%% Meta: b = 3\ndirection LR\na[\"A\"]\na --> a\n";
+    let mut root = Parser::syntax(src);
+    let tags = |root: &graphrite_core::cst::SyntaxNode| -> Vec<(String, String)> {
+        let meta = root.document().metadata.unwrap();
+        assert_eq!(meta.title.as_deref(), Some("T"));
+        meta.tags.into_iter().flatten().collect()
+    };
+    assert_eq!(
+        tags(&root),
+        [("a".into(), "1".into()), ("b".into(), "3".into())]
+    );
+
+    // dropping the second meta line from the tree drops its tags
+    let Some(Element::Node(header)) = root.children.first_mut() else {
+        panic!("no header node")
+    };
+    assert_eq!(header.kind, NodeKind::Header);
+    header.children.retain(|c| !c.to_string().contains("b = 3"));
+    assert_eq!(tags(&root), [("a".into(), "1".into())]);
+}
@@ -1,4 +1,4 @@
-use graphrite_core::parser::Parser;
+use graphrite_core::parser::{ParseOptions, Parser};
 
 #[test]
 fn missing_direction_has_span() {
@@ -14,3 +14,55 @@ fn node_label_must_be_quoted_error() {
     let errs = Parser::parse(src).unwrap_err();
     assert!(errs.iter().any(|e| e.code == "E0003"));
 }
+
+#[test]
+fn missing_title_header_error() {
+    let src = "direction LR\na[\"A\"]\na --> a\n";
+    let errs = Parser::parse(src).unwrap_err();
+    let e = errs.iter().find(|e| e.code == "E0002").unwrap();
+    assert_eq!(e.span.as_ref().unwrap().start.line, 1);
+}
+
+#[test]
+fn empty_title_header_error() {
+    let src = "%% Diagram:\ndirection LR\na[\"A\"]\na --> a\n";
+    let errs = Parser::parse(src).unwrap_err();
+    assert!(errs.iter().any(|e| e.code == "E0002"));
+}
+
+#[test]
+fn missing_title_allowed_with_option() {
+    let src = "direction LR\na[\"A\"]\na --> a\n";
+    let opts = ParseOptions {
+        allow_missing_title: true,
+    };
+    let doc = Parser::parse_with(src, &opts).unwrap();
+    assert!(doc.metadata.is_none());
+}
+
+#[test]
+fn reports_all_stages_in_one_pass() {
+    let src =
+        "%% Diagram: T\ndirection LR\nFoo[\"Foo\"]\nbar[\"Bar\"]\nbaz[\"Baz\"]\nbar --> qux\n";
+    let errs = Parser::parse(src).unwrap_err();
+    let codes: Vec<&str> = errs.iter().map(|e| e.code.as_str()).collect();
+    assert_eq!(codes, ["E0100", "E0203", "E0203", "E0202"]);
+}
+
+#[test]
+fn diagnostics_sorted_by_span_and_deduplicated() {
+    let src = "%% Diagram: T\ndirection LR\nghost --> ghost\na[\"A\"]\n";
+    let errs = Parser::parse(src).unwrap_err();
+    let lines: Vec<usize> = errs
+        .iter()
+        .map(|e| e.span.as_ref().unwrap().start.line)
+        .collect();
+    let mut sorted = lines.clone();
+    sorted.sort();
+    assert_eq!(lines, sorted);
+    for w in errs.windows(2) {
+        assert_ne!(w[0], w[1]);
+    }
+    assert!(errs.iter().any(|e| e.code == "E0201"));
+    assert!(errs.iter().any(|e| e.code == "E0203"));
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::run_cli;
+
+/// Renders a two-node chain `a --> b` laid out in `dir`.
+fn render(dir: &str, format: &str) -> String {
+    let path = std::env::temp_dir().join(format!("graphrite_direction_{}.mmd", dir));
+    std::fs::write(
+        &path,
+        format!(
+            "%% Diagram: T\ndirection {}\na[\"A\"]\nb[\"B\"]\na --> b\n",
+            dir
+        ),
+    )
+    .unwrap();
+    let (code, out, err) = run_cli(&["render", "--format", format, path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", err);
+    out
+}
+
+fn num(s: &str, attr: &str) -> f32 {
+    let at = s.find(&format!(" {}=\"", attr)).unwrap() + attr.len() + 3;
+    s[at..].split('"').next().unwrap().parse().unwrap()
+}
+
+/// Top-left corners of the boxes of `a` and `b`, and the first and last edge points.
+fn layout(svg: &str) -> [(f32, f32); 4] {
+    let corner = |label: &str| {
+        let text = &svg[..svg.find(label).unwrap()];
+        let rect = &text[text.rfind("<rect").unwrap()..];
+        (num(rect, "x"), num(rect, "y"))
+    };
+    let line = svg.lines().find(|l| l.contains("<polyline")).unwrap();
+    let points: Vec<(f32, f32)> = line
+        .split("points=\"")
+        .nth(1)
+        .unwrap()
+        .trim_end_matches("\"/>")
+        .split(' ')
+        .map(|p| {
+            let (x, y) = p.split_once(',').unwrap();
+            (x.parse().unwrap(), y.parse().unwrap())
+        })
+        .collect();
+    [
+        corner(">A</text>"),
+        corner(">B</text>"),
+        points[0],
+        *points.last().unwrap(),
+    ]
+}
+
+#[test]
+fn dot_sets_rankdir() {
+    for (dir, rankdir) in [
+        ("LR", "LR"),
+        ("RL", "RL"),
+        ("TD", "TB"),
+        ("TB", "TB"),
+        ("BT", "BT"),
+    ] {
+        let out = render(dir, "dot");
+        assert!(
+            out.contains(&format!("  rankdir={};\n", rankdir)),
+            "{}: {}",
+            dir,
+            out
+        );
+    }
+}
+
+#[test]
+fn rl_mirrors_lr() {
+    let [a, b, start, end] = layout(&render("RL", "svg"));
+    assert_eq!(a.1, b.1);
+    assert!(b.0 < a.0, "b should be left of a");
+    // leaves a on its left side and enters b on its right side
+    assert_eq!(start, (a.0, a.1 + 24.0));
+    assert_eq!(end, (b.0 + 160.0, b.1 + 24.0));
+
+    let [a, b, start, end] = layout(&render("LR", "svg"));
+    assert!(a.0 < b.0);
+    assert_eq!(start, (a.0 + 160.0, a.1 + 24.0));
+    assert_eq!(end, (b.0, b.1 + 24.0));
+}
+
+#[test]
+fn bt_mirrors_td() {
+    let [a, b, start, end] = layout(&render("BT", "svg"));
+    assert_eq!(a.0, b.0);
+    assert!(b.1 < a.1, "b should be above a");
+    assert_eq!(start, (a.0 + 80.0, a.1));
+    assert_eq!(end, (b.0 + 80.0, b.1 + 48.0));
+
+    let [a, b, start, end] = layout(&render("TD", "svg"));
+    assert!(a.1 < b.1);
+    assert_eq!(start, (a.0 + 80.0, a.1 + 48.0));
+    assert_eq!(end, (b.0 + 80.0, b.1));
+    assert_eq!(render("TB", "svg"), render("TD", "svg"));
+}
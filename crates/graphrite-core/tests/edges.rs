@@ -1,8 +1,11 @@
+mod common;
+
+use common::run_cli;
 use graphrite_core::parser::Parser;
 
 #[test]
 fn parses_flow_and_conditional_edges() {
-    let src = "direction LR\na[\"A\"]\nb[\"B\"]\na --> b\na -.-> b\n";
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na --> b\na -.-> b\n";
     let doc = Parser::parse(src).unwrap();
     assert_eq!(doc.edges.len(), 2);
     assert!(matches!(
@@ -17,7 +20,128 @@ fn parses_flow_and_conditional_edges() {
 
 #[test]
 fn edge_spans_present() {
-    let src = "direction LR\na[\"A\"]\nb[\"B\"]\na --> b\n";
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na --> b\n";
     let doc = Parser::parse(src).unwrap();
     assert!(doc.edges[0].span.is_some());
 }
+
+#[test]
+fn parses_edge_labels() {
+    let src =
+        "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -->|HTTPS| b\na -.->|\"maybe | not\"| b\na --> b\n";
+    let doc = Parser::parse(src).unwrap();
+    assert_eq!(doc.edges.len(), 3);
+    assert_eq!(doc.edges[0].to, "b");
+    assert_eq!(doc.edges[0].label.as_deref(), Some("HTTPS"));
+    assert_eq!(doc.edges[1].label.as_deref(), Some("maybe | not"));
+    assert_eq!(doc.edges[2].label, None);
+}
+
+#[test]
+fn padded_label_span_covers_the_text() {
+    let src =
+        "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -->| yes | b\na -->|  \"no\" | b\n";
+    let doc = Parser::parse(src).unwrap();
+    let text = |i: usize| {
+        let span = doc.edges[i].label_span.as_ref().unwrap();
+        assert_eq!(
+            span.end.col - span.start.col,
+            span.end.offset - span.start.offset
+        );
+        &src[span.start.offset..span.end.offset]
+    };
+    assert_eq!(doc.edges[0].label.as_deref(), Some("yes"));
+    assert_eq!(text(0), "yes");
+    assert_eq!(doc.edges[0].label_span.as_ref().unwrap().start.col, 8);
+    assert_eq!(text(1), "\"no\"");
+}
+
+#[test]
+fn empty_label_is_no_label() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -->|| b\na -->| | b\n";
+    let doc = Parser::parse(src).unwrap();
+    for e in &doc.edges {
+        assert_eq!((e.label.as_deref(), &e.label_span), (None, &None));
+    }
+    assert!(graphrite_core::fmt::format(src)
+        .unwrap()
+        .ends_with("a --> b\na --> b\n"));
+}
+
+#[test]
+fn edge_label_keeps_punctuation() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -->|XRPC/ATProto| b\n";
+    let doc = Parser::parse(src).unwrap();
+    assert_eq!(doc.edges[0].label.as_deref(), Some("XRPC/ATProto"));
+}
+
+#[test]
+fn svg_draws_conditional_edges_dashed() {
+    let path = std::env::temp_dir().join("graphrite_edge_kinds.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\nc[\"C\"]\na --> b\na -.->|retry| c\n",
+    )
+    .unwrap();
+    let (code, out, err) = run_cli(&["render", "--format", "svg", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", err);
+    let edges: Vec<&str> = out.lines().filter(|l| l.contains("<polyline")).collect();
+    assert_eq!(edges.len(), 2, "{}", out);
+    assert!(!edges[0].contains("stroke-dasharray"), "{}", edges[0]);
+    assert!(
+        edges[1].contains(" stroke-dasharray=\"6 4\""),
+        "{}",
+        edges[1]
+    );
+    // the legend explains the dashed line
+    let legend = &out[out.find("<g id=\"legend\">").unwrap()..];
+    assert!(legend.contains("stroke-dasharray"), "{}", legend);
+    assert!(legend.contains(">Conditional</text>"), "{}", legend);
+}
+
+#[test]
+fn legend_fits_every_entry_with_inferred_shapes() {
+    let path = std::env::temp_dir().join("graphrite_legend_infer.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -.-> b\n",
+    )
+    .unwrap();
+    let (code, out, err) = run_cli(&[
+        "render",
+        "--format",
+        "svg",
+        "--infer-shapes",
+        path.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0, "{}", err);
+    let attr = |s: &str, name: &str| -> Option<f32> {
+        let at = s.find(&format!(" {}=\"", name))? + name.len() + 3;
+        s[at..].split('"').next()?.parse().ok()
+    };
+    let legend = &out[out.find("<g id=\"legend\">").unwrap()..];
+    let mut lines = legend.lines().skip(1);
+    let frame = lines.next().unwrap();
+    let (x0, y0) = (attr(frame, "x").unwrap(), attr(frame, "y").unwrap());
+    let (x1, y1) = (
+        x0 + attr(frame, "width").unwrap(),
+        y0 + attr(frame, "height").unwrap(),
+    );
+    let svg_width = attr(&out, "width").unwrap();
+    assert!(x1 <= svg_width, "legend wider than the image");
+    assert!(legend.contains(">Error</text>") && legend.contains(">Conditional</text>"));
+    for line in lines.take_while(|l| !l.contains("</g>")) {
+        // labels are at most 11 characters of 12px sans-serif
+        let right = if line.contains("<text") {
+            attr(line, "x").map(|x| x + 80.0)
+        } else {
+            attr(line, "x2").or_else(|| Some(attr(line, "x")? + attr(line, "width")?))
+        };
+        if let Some(right) = right {
+            assert!(right <= x1, "{} sticks out of the legend", line);
+        }
+        if let Some(y) = attr(line, "y").or_else(|| attr(line, "y2")) {
+            assert!(y0 <= y && y <= y1, "{} sticks out of the legend", line);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::run_cli;
+use graphrite_core::fix::apply_fixes;
+use graphrite_core::parser::Parser;
+
+fn fix_once(src: &str) -> String {
+    let errs = Parser::parse(src).unwrap_err();
+    apply_fixes(src, &errs).0
+}
+
+#[test]
+fn quotes_unquoted_label() {
+    let src = "%% Diagram: T\ndirection LR\na[Unquoted Label]\nb[\"B\"]\na --> b\n";
+    let errs = Parser::parse(src).unwrap_err();
+    let e = errs.iter().find(|e| e.code == "E0003").unwrap();
+    assert_eq!(e.fixes.len(), 1);
+    assert!(!errs.iter().any(|e| e.code == "E0010"));
+    let fixed = fix_once(src);
+    assert!(fixed.contains("a[\"Unquoted Label\"]"));
+    assert!(Parser::parse(&fixed).is_ok());
+}
+
+#[test]
+fn renames_id_everywhere() {
+    let src = "%% Diagram: T\ndirection LR\nFooBar[\"F\"]\nb[\"B\"]\nFooBar --> b\nb --> FooBar\n";
+    let fixed = fix_once(src);
+    assert_eq!(
+        fixed,
+        "%% Diagram: T\ndirection LR\nfoo_bar[\"F\"]\nb[\"B\"]\nfoo_bar --> b\nb --> foo_bar\n"
+    );
+    assert!(Parser::parse(&fixed).is_ok());
+}
+
+fn snake_case_fixes(body: &str) -> usize {
+    let src = format!("%% Diagram: T\ndirection LR\n{}", body);
+    let errs = Parser::parse(&src).unwrap_err();
+    let e = errs.iter().find(|e| e.code == "E0100").unwrap();
+    e.fixes.len()
+}
+
+#[test]
+fn rename_skips_ids_already_declared() {
+    assert_eq!(
+        snake_case_fixes("FooBar[\"F\"]\nfoo_bar[\"G\"]\nFooBar --> foo_bar\n"),
+        0
+    );
+}
+
+#[test]
+fn rename_skips_group_ids() {
+    assert_eq!(
+        snake_case_fixes("Tier[\"T\"]\nsubgraph tier[\"Tier\"]\n    Tier\nend\nTier --> Tier\n"),
+        0
+    );
+}
+
+#[test]
+fn rename_skips_reserved_words() {
+    assert_eq!(snake_case_fixes("End[\"E\"]\nEnd --> End\n"), 0);
+    assert_eq!(
+        snake_case_fixes("Subgraph[\"S\"]\nSubgraph --> Subgraph\n"),
+        0
+    );
+}
+
+#[test]
+fn collapses_whitespace_on_long_line() {
+    let src = format!(
+        "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na{}-->{}b\n",
+        " ".repeat(60),
+        " ".repeat(60)
+    );
+    let errs = Parser::parse(&src).unwrap_err();
+    assert!(errs
+        .iter()
+        .any(|e| e.code == "E0300" && !e.fixes.is_empty()));
+    let fixed = apply_fixes(&src, &errs).0;
+    assert!(fixed.ends_with("\na --> b\n"));
+}
+
+#[test]
+fn wraps_long_label_over_lines() {
+    let label = "word ".repeat(30);
+    let src = format!(
+        "%% Diagram: T\ndirection LR\n  a[\"{}\"]\nb[\"B\"]\na --> b\n",
+        label.trim_end()
+    );
+    let errs = Parser::parse(&src).unwrap_err();
+    let e = errs.iter().find(|e| e.code == "E0300").unwrap();
+    assert_eq!(e.fixes[0].message, "continue the label on the next line");
+    let fixed = apply_fixes(&src, &errs).0;
+    assert!(fixed.lines().all(|l| l.len() <= 100), "{}", fixed);
+    assert!(fixed.contains("\n      \"word"), "{}", fixed);
+    let doc = Parser::parse(&fixed).unwrap();
+    assert_eq!(doc.nodes[0].label, label.trim_end());
+}
+
+#[test]
+fn no_fix_for_unfixable_errors() {
+    let errs = Parser::parse("%% Diagram: T\ndirection LR\na --> b\n").unwrap_err();
+    assert!(errs.iter().all(|e| e.fixes.is_empty()));
+}
+
+#[test]
+fn fix_without_offsets_is_skipped() {
+    let src = "%% Diagram: T\ndirection LR\nFoo[\"F\"]\nFoo --> Foo\n";
+    // line 3, columns 1-4, as a client that only knows lines and columns would send it
+    let fix: graphrite_core::error::Fix = serde_json::from_str(
+        r#"{"message": "rename", "edits": [{"span": {"start": {"line": 3, "col": 1}, "end": {"line": 3, "col": 4}}, "replacement": "foo"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        graphrite_core::fix::apply(src, [&fix]),
+        (src.to_string(), 0)
+    );
+}
+
+#[test]
+fn fixes_serialized_in_diag_json() {
+    let path = std::env::temp_dir().join("graphrite_fix_json.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\nFoo[\"F\"]\nFoo --> Foo\n",
+    )
+    .unwrap();
+    let (code, out, _) = run_cli(&["lint", "--diag-json", path.to_str().unwrap()]);
+    assert_eq!(code, 1);
+    assert!(out.contains("\"fixes\""));
+    assert!(out.contains("\"replacement\": \"foo\""));
+}
+
+#[test]
+fn fix_command_rewrites_in_place() {
+    let path = std::env::temp_dir().join("graphrite_fix_in_place.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\nStart[Begin Here]\nDone[\"End\"]\nStart --> Done\n",
+    )
+    .unwrap();
+    let (code, _, err) = run_cli(&["fix", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "stderr: {}", err);
+    let fixed = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        fixed,
+        "%% Diagram: T\ndirection LR\nstart[\"Begin Here\"]\ndone[\"End\"]\nstart --> done\n"
+    );
+}
+
+#[test]
+fn split_statement_keeps_its_indentation() {
+    let src = "%% Diagram: T\n\ndirection LR\n\nsubgraph g[\"G\"]\n    a[\"A\"] b[\"B\"]\nend\n\na --> b\n";
+    let fixed = fix_once(src);
+    assert_eq!(
+        fixed,
+        "%% Diagram: T\n\ndirection LR\n\nsubgraph g[\"G\"]\n    a[\"A\"]\n    b[\"B\"]\nend\n\na --> b\n"
+    );
+    // no fmt pass is needed afterwards
+    assert_eq!(graphrite_core::fmt::format(&fixed).unwrap(), fixed);
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use common::run_cli;
+use graphrite_core::fmt::format;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+/// A fresh scratch directory under the system temp dir.
+fn scratch(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    dir
+}
+
+fn valid_samples() -> Vec<(String, String)> {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../samples/valid");
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let p = entry.unwrap().path();
+        if p.extension().and_then(|s| s.to_str()) == Some("mmd") {
+            let name = p.file_name().unwrap().to_str().unwrap().to_string();
+            out.push((name, std::fs::read_to_string(&p).unwrap()));
+        }
+    }
+    assert!(!out.is_empty());
+    out
+}
+
+#[test]
+fn formatting_is_idempotent_on_samples() {
+    for (name, src) in valid_samples() {
+        let once = format(&src).unwrap();
+        let twice = format(&once).unwrap();
+        assert_eq!(once, twice, "{} is not stable under fmt", name);
+    }
+}
+
+#[test]
+fn formatting_preserves_the_document() {
+    for (name, src) in valid_samples() {
+        let before = Parser::parse(&src).unwrap();
+        let after = Parser::parse(&format(&src).unwrap())
+            .unwrap_or_else(|e| panic!("{} no longer parses: {:?}", name, e));
+        let ids = |d: &graphrite_core::ast::Document| {
+            let mut v: Vec<_> = d
+                .nodes
+                .iter()
+                .map(|n| (n.id.clone(), n.label.clone()))
+                .collect();
+            v.sort();
+            v
+        };
+        assert_eq!(ids(&before), ids(&after), "{}", name);
+        assert_eq!(before.edges.len(), after.edges.len(), "{}", name);
+        assert_eq!(before.metadata, after.metadata, "{}", name);
+    }
+}
+
+#[test]
+fn formatted_output_passes_layout_rules() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na-->b\na -->|x|   b a --> b\n";
+    let out = format(src).unwrap();
+    let (_, diags) = Parser::analyze(&out, &ParseOptions::default(), &Registry::empty());
+    assert!(
+        diags
+            .iter()
+            .all(|d| !["E0310", "E0311"].contains(&d.code.as_str())),
+        "{:?}",
+        diags
+    );
+}
+
+#[test]
+fn canonical_layout() {
+    let src = "%% Meta: owner=ops\n%% notes\n%% Diagram: T\ndirection   LR\na --> b\n%% the start\na[\"A\"]\nb[\"B\"]\n";
+    let out = format(src).unwrap();
+    assert_eq!(
+        out,
+        "%% Diagram: T\n%% Meta: owner=ops\n%% notes\n\ndirection LR\n\n%% the start\na[\"A\"]\nb[\"B\"]\n\na --> b\n"
+    );
+}
+
+#[test]
+fn comments_and_blank_lines_are_kept() {
+    let src = "%% Diagram: T\ndirection LR\n\n%% inputs\na[\"A\"]\n\n%% outputs\nb[\"B\"] %% sink\na --> b\n%% end\n";
+    let out = format(src).unwrap();
+    assert_eq!(
+        out,
+        "%% Diagram: T\n\ndirection LR\n\n%% inputs\na[\"A\"]\n\n%% outputs\n%% sink\nb[\"B\"]\n\na --> b\n\n%% end\n"
+    );
+}
+
+#[test]
+fn labels_are_quoted_and_left_alone() {
+    let src = "%% Diagram: T\ndirection LR\na[Hello World]\nb[\"x --> \\\"y\\\" \\\\ z\"]\na -.->|maybe| b\nb -->|\"q\"| a\n";
+    let out = format(src).unwrap();
+    assert!(out.contains("a[\"Hello World\"]\n"), "{}", out);
+    assert!(out.contains("b[\"x --> \\\"y\\\" \\\\ z\"]\n"), "{}", out);
+    assert!(out.contains("a -.->|maybe| b\n"), "{}", out);
+    assert!(out.contains("b -->|\"q\"| a\n"), "{}", out);
+}
+
+#[test]
+fn refuses_source_it_cannot_parse() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"] ]\n";
+    let errs = format(src).unwrap_err();
+    assert!(errs.iter().any(|d| d.code == "E0011"), "{:?}", errs);
+}
+
+#[test]
+fn samples_are_formatted() {
+    for (name, src) in valid_samples() {
+        assert_eq!(format(&src).unwrap(), src, "{} needs `graphrite fmt`", name);
+    }
+}
+
+#[test]
+fn long_labels_are_wrapped() {
+    let label = format!("{} {}", "Label ".repeat(25).trim_end(), "x".repeat(120));
+    let src = format!(
+        "%% Diagram: T\ndirection LR\nnode_a[\"{}\"]\nnode_a --> node_a\n",
+        label
+    );
+    let out = format(&src).unwrap();
+    assert!(out.lines().all(|l| l.chars().count() <= 100), "{}", out);
+    assert!(out.contains("node_a[\"Label Label"), "{}", out);
+    assert!(out.contains("\n    \"x"), "{}", out);
+    assert_eq!(format(&out).unwrap(), out);
+    let doc = Parser::parse(&out).unwrap();
+    assert_eq!(doc.nodes[0].label, label);
+}
+
+#[test]
+fn short_continued_labels_are_joined() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A \"\n    \"B\"]\na --> a\n";
+    assert!(format(src).unwrap().contains("\na[\"A B\"]\n"));
+}
+
+#[test]
+fn check_prints_unified_diff() {
+    let dir = scratch("graphrite_fmt_diff");
+    let sample = include_str!("../../../samples/valid/bluesky.mmd");
+    let src = sample
+        .replace("\n\ndirection LR", "\ndirection LR")
+        .replace("third_pds --> federation", "third_pds  -->  federation");
+    let path = dir.join("bluesky.mmd");
+    std::fs::write(&path, &src).unwrap();
+    let p = path.to_str().unwrap();
+    let (code, out, err) = run_cli(&["fmt", "--check", p]);
+    assert_eq!(code, 1);
+    let expected = format!(
+        "--- {p}\n+++ {p}\n\
+         @@ -1,5 +1,6 @@\n \
+         %% Diagram: Bluesky-style federated microblogging service\n \
+         %% Meta: type=architecture; domain=social; version=1\n\
+         +\n \
+         direction LR\n \n \
+         user_app[\"Mobile/Web Client\"]\n\
+         @@ -36,4 +37,4 @@\n \
+         federation -->|XRPC/ATProto| third_pds\n \
+         pds_api --> federation\n \
+         user_app --> pds_api\n\
+         -third_pds  -->  federation\n\
+         +third_pds --> federation\n"
+    );
+    assert_eq!(out, expected);
+    assert!(
+        err.contains("1 of 1 file(s) would be reformatted"),
+        "{}",
+        err
+    );
+    // --check never writes
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), src);
+}
+
+#[test]
+fn check_and_write_walk_directories() {
+    let dir = scratch("graphrite_fmt_dirs");
+    let clean = include_str!("../../../samples/valid/simple_flow.mmd");
+    let messy = "%% Diagram: T\ndirection LR\na[\"A\"]\na-->a\n";
+    std::fs::write(dir.join("clean.mmd"), clean).unwrap();
+    std::fs::write(dir.join("nested/messy.graphrite"), messy).unwrap();
+    std::fs::write(dir.join("nested/notes.txt"), "not a diagram").unwrap();
+    let d = dir.to_str().unwrap();
+
+    let (code, out, err) = run_cli(&["fmt", "--check", d]);
+    assert_eq!(code, 1);
+    assert!(out.contains("messy.graphrite\n"), "{}", out);
+    assert!(!out.contains("clean.mmd"), "{}", out);
+    assert!(
+        err.contains("1 of 2 file(s) would be reformatted"),
+        "{}",
+        err
+    );
+
+    let (code, _, err) = run_cli(&["fmt", "--write", d]);
+    assert_eq!(code, 0, "{}", err);
+    assert!(err.contains("reformatted 1 of 2 file(s)"), "{}", err);
+    let (code, out, err) = run_cli(&["fmt", "--check", d]);
+    assert_eq!((code, out.as_str()), (0, ""));
+    assert!(
+        err.contains("0 of 2 file(s) would be reformatted"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn several_files_need_check_or_write() {
+    let dir = scratch("graphrite_fmt_many");
+    let src = include_str!("../../../samples/valid/simple_flow.mmd");
+    std::fs::write(dir.join("a.mmd"), src).unwrap();
+    std::fs::write(dir.join("b.mmd"), src).unwrap();
+    let (code, _, err) = run_cli(&["fmt", dir.to_str().unwrap()]);
+    assert_eq!(code, 1);
+    assert!(err.contains("--check or --write"), "{}", err);
+}
+
+#[test]
+fn check_and_write_conflict() {
+    let dir = scratch("graphrite_fmt_conflict");
+    let messy = "%% Diagram: T\ndirection LR\na[\"A\"]\na-->a\n";
+    let path = dir.join("messy.mmd");
+    std::fs::write(&path, messy).unwrap();
+    let (code, _, err) = run_cli(&["fmt", "--check", "--write", path.to_str().unwrap()]);
+    assert_eq!(code, 2);
+    assert!(err.contains("cannot be used with"), "{}", err);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), messy);
+}
+
+#[test]
+fn subgraphs_are_indented_and_edges_move_out() {
+    let src = "%% Diagram: T\ndirection LR\nsubgraph outer[\"Outer\"]\na[\"A\"]\n      %% inner tier\n subgraph inner[\"Inner\"]\nb[\"B\"]\na --> b\n%% closing\nend\nend\n";
+    let out = format(src).unwrap();
+    assert_eq!(
+        out,
+        "%% Diagram: T\n\ndirection LR\n\nsubgraph outer[\"Outer\"]\n    a[\"A\"]\n    %% inner tier\n    subgraph inner[\"Inner\"]\n        b[\"B\"]\n        %% closing\n    end\nend\n\na --> b\n"
+    );
+    assert_eq!(format(&out).unwrap(), out);
+}
+
+#[test]
+fn refuses_unclosed_subgraphs() {
+    let src = "%% Diagram: T\ndirection LR\nsubgraph g[\"G\"]\na[\"A\"]\na --> a\n";
+    let errs = format(src).unwrap_err();
+    assert_eq!(errs[0].code, "E0012");
+}
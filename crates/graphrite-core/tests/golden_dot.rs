@@ -1,11 +1,23 @@
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
-fn run_cli(args: &[&str]) -> (i32, String, String) {
-    let output = Command::new("cargo")
+/// Runs the CLI with `input` on stdin, so output never depends on file paths.
+fn run_cli_stdin(args: &[&str], input: &str) -> (i32, String, String) {
+    let mut child = Command::new("cargo")
         .args(["run", "-q", "-p", "graphrite-cli", "--"])
         .args(args)
-        .output()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .expect("run cli");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().expect("wait for cli");
     (
         output.status.code().unwrap_or(1),
         String::from_utf8_lossy(&output.stdout).into_owned(),
@@ -13,201 +25,151 @@ fn run_cli(args: &[&str]) -> (i32, String, String) {
     )
 }
 
-fn assert_dot_contains(path: &str, needles: &[&str]) {
-    let (code, out, err) = run_cli(&["render", "--format", "dot", path]);
+fn render_dot(src: &str) -> String {
+    let (code, out, err) = run_cli_stdin(&["render", "--format", "dot"], src);
     assert_eq!(code, 0, "stderr: {}", err);
-    for n in needles {
-        assert!(out.contains(n), "missing: {}", n);
+    out
+}
+
+/// Compares the DOT for `src` with a file under `tests/golden/`, regenerated with
+/// `graphrite render --format dot < samples/valid/<name>.mmd`.
+fn assert_golden(src: &str, golden: &str) {
+    let out = render_dot(src);
+    if out != golden {
+        for (i, (a, b)) in out.lines().zip(golden.lines()).enumerate() {
+            assert_eq!(a, b, "first difference on line {}", i + 1);
+        }
+        assert_eq!(out, golden);
     }
 }
 
 #[test]
 fn bluesky_golden_dot_stable() {
-    assert_dot_contains(
-        "/Users/bwl/Developer/aiaide/samples/valid/bluesky.mmd",
-        &[
-            "digraph G",
-            "user_app [label=\"Mobile/Web Client\"];",
-            "user_app -> pds_api;",
-        ],
+    assert_golden(
+        include_str!("../../../samples/valid/bluesky.mmd"),
+        include_str!("golden/bluesky.dot"),
     );
 }
 
 #[test]
 fn simple_flow_golden_dot() {
-    assert_dot_contains(
-        "/Users/bwl/Developer/aiaide/samples/valid/simple_flow.mmd",
-        &[
-            "start [label=\"Start\"];",
-            "validate [label=\"Validate Input\"];",
-            "queue [label=\"Queue\"];",
-            "worker1 [label=\"Worker 1\"];",
-            "worker2 [label=\"Worker 2\"];",
-            "process [label=\"Process\"];",
-            "store [label=\"Store\"];",
-            "notify [label=\"Notify\"];",
-            "archive [label=\"Archive\"];",
-            "metrics [label=\"Metrics\"];",
-            "end_success [label=\"Success\"];",
-            "end_fail [label=\"Fail\"];",
-            "start -> validate;",
-            "queue -> worker1;",
-            "queue -> worker2;",
-            "worker1 -> process;",
-            "worker2 -> process;",
-            "process -> store;",
-            "process -> notify;",
-            "store -> archive;",
-            "store -> metrics;",
-            "notify -> end_success;",
-            "archive -> end_success;",
-            "validate -> end_fail;",
-        ],
+    assert_golden(
+        include_str!("../../../samples/valid/simple_flow.mmd"),
+        include_str!("golden/simple_flow.dot"),
     );
 }
 
 #[test]
 fn very_parallel_golden_dot() {
-    assert_dot_contains(
-        "/Users/bwl/Developer/aiaide/samples/valid/very_parallel.mmd",
-        &[
-            "ingest [label=\"Ingest\"];",
-            "precheck [label=\"Precheck\"];",
-            "router [label=\"Router\"];",
-            "proc_a [label=\"Proc A\"];",
-            "proc_b [label=\"Proc B\"];",
-            "proc_c [label=\"Proc C\"];",
-            "proc_d [label=\"Proc D\"];",
-            "proc_e [label=\"Proc E\"];",
-            "proc_f [label=\"Proc F\"];",
-            "merge1 [label=\"Merge 1\"];",
-            "merge2 [label=\"Merge 2\"];",
-            "finalize [label=\"Finalize\"];",
-            "out [label=\"Out\"];",
-            "ingest -> precheck;",
-            "precheck -> router;",
-            "router -> proc_a;",
-            "router -> proc_b;",
-            "router -> proc_c;",
-            "router -> proc_d;",
-            "router -> proc_e;",
-            "router -> proc_f;",
-            "proc_a -> merge1;",
-            "proc_b -> merge1;",
-            "proc_c -> merge1;",
-            "proc_d -> merge2;",
-            "proc_e -> merge2;",
-            "proc_f -> merge2;",
-            "merge1 -> finalize;",
-            "merge2 -> finalize;",
-            "finalize -> out;",
-        ],
+    assert_golden(
+        include_str!("../../../samples/valid/very_parallel.mmd"),
+        include_str!("golden/very_parallel.dot"),
     );
 }
 
 #[test]
 fn maintenance_loop_golden_dot() {
-    assert_dot_contains(
-        "/Users/bwl/Developer/aiaide/samples/valid/maintenance_loop.mmd",
-        &[
-            "monitor [label=\"Monitor\"];",
-            "alert [label=\"Alert\"];",
-            "classify [label=\"Classify\"];",
-            "triage [label=\"Triage\"];",
-            "assign [label=\"Assign\"];",
-            "fix [label=\"Fix\"];",
-            "review [label=\"Review\"];",
-            "verify [label=\"Verify\"];",
-            "release [label=\"Release\"];",
-            "postmortem [label=\"Postmortem\"];",
-            "backlog [label=\"Backlog\"];",
-            "monitor -> alert;",
-            "alert -> classify;",
-            "classify -> triage;",
-            "triage -> assign;",
-            "assign -> fix;",
-            "fix -> review;",
-            "review -> verify;",
-            "verify -> release;",
-            "release -> postmortem;",
-            "postmortem -> backlog;",
-            "backlog -> monitor;",
-        ],
+    assert_golden(
+        include_str!("../../../samples/valid/maintenance_loop.mmd"),
+        include_str!("golden/maintenance_loop.dot"),
     );
 }
 
 #[test]
 fn thought_pattern_golden_dot() {
-    assert_dot_contains(
-        "/Users/bwl/Developer/aiaide/samples/valid/thought_pattern.mmd",
-        &[
-            "observe [label=\"Observe\"];",
-            "collect [label=\"Collect Data\"];",
-            "orient [label=\"Orient\"];",
-            "hypothesize [label=\"Hypothesize\"];",
-            "decide [label=\"Decide\"];",
-            "plan [label=\"Plan\"];",
-            "act [label=\"Act\"];",
-            "reflect [label=\"Reflect\"];",
-            "improve [label=\"Improve\"];",
-            "repeat [label=\"Repeat\"];",
-            "observe -> collect;",
-            "collect -> orient;",
-            "orient -> hypothesize;",
-            "hypothesize -> decide;",
-            "decide -> plan;",
-            "plan -> act;",
-            "act -> reflect;",
-            "reflect -> improve;",
-            "improve -> repeat;",
-            "repeat -> observe;",
-        ],
+    assert_golden(
+        include_str!("../../../samples/valid/thought_pattern.mmd"),
+        include_str!("golden/thought_pattern.dot"),
     );
 }
 
 #[test]
 fn us_highway_system_golden_dot() {
-    assert_dot_contains(
-        "/Users/bwl/Developer/aiaide/samples/valid/us_highway_system.mmd",
-        &[
-            "nyc [label=\"New York\"];",
-            "bos [label=\"Boston\"];",
-            "phl [label=\"Philadelphia\"];",
-            "dc [label=\"Washington DC\"];",
-            "chi [label=\"Chicago\"];",
-            "den [label=\"Denver\"];",
-            "phx [label=\"Phoenix\"];",
-            "la [label=\"Los Angeles\"];",
-            "sd [label=\"San Diego\"];",
-            "sf [label=\"San Francisco\"];",
-            "sea [label=\"Seattle\"];",
-            "por [label=\"Portland\"];",
-            "slt [label=\"Salt Lake City\"];",
-            "atl [label=\"Atlanta\"];",
-            "mia [label=\"Miami\"];",
-            "no [label=\"New Orleans\"];",
-            "hou [label=\"Houston\"];",
-            "dal [label=\"Dallas\"];",
-            "sa [label=\"San Antonio\"];",
-            "nyc -> bos;",
-            "nyc -> phl;",
-            "phl -> dc;",
-            "nyc -> chi;",
-            "dc -> atl;",
-            "chi -> den;",
-            "den -> slt;",
-            "slt -> sf;",
-            "sf -> la;",
-            "la -> sd;",
-            "sea -> por;",
-            "por -> sf;",
-            "atl -> mia;",
-            "atl -> no;",
-            "no -> hou;",
-            "hou -> dal;",
-            "dal -> sa;",
-            "sa -> phx;",
-            "phx -> la;",
-        ],
+    assert_golden(
+        include_str!("../../../samples/valid/us_highway_system.mmd"),
+        include_str!("golden/us_highway_system.dot"),
+    );
+}
+
+#[test]
+fn service_tiers_golden_dot() {
+    assert_golden(
+        include_str!("../../../samples/valid/service_tiers.mmd"),
+        include_str!("golden/service_tiers.dot"),
+    );
+}
+
+#[test]
+fn rube_goldberg_posting_golden_dot() {
+    assert_golden(
+        include_str!("../../../samples/valid/rube_goldberg_posting.mmd"),
+        include_str!("golden/rube_goldberg_posting.dot"),
+    );
+}
+
+#[test]
+fn multilingual_golden_dot() {
+    assert_golden(
+        include_str!("../../../samples/valid/multilingual.mmd"),
+        include_str!("golden/multilingual.dot"),
+    );
+}
+
+#[test]
+fn service_tiers_golden_dot_dashes_conditional_edges() {
+    let out = render_dot(include_str!("../../../samples/valid/service_tiers.mmd"));
+    for needle in [
+        "api -> mailer [label=\"enqueue\", style=dashed];",
+        "api -> thumbnailer [label=\"enqueue\", style=dashed];",
+        "api -> postgres;",
+        "thumbnailer -> blobs;",
+    ] {
+        assert!(out.contains(needle), "missing: {}", needle);
+    }
+    assert_eq!(out.matches("style=dashed").count(), 2, "{}", out);
+}
+
+#[test]
+fn direction_and_title_become_graph_attributes() {
+    let out = render_dot("%% Diagram: Say \"hi\" \\o/\ndirection BT\na[\"A\"]\na --> a\n");
+    assert!(out.starts_with("digraph G {\n  rankdir=BT;\n"), "{}", out);
+    assert!(
+        out.contains("  label=\"Say \\\"hi\\\" \\\\o/\";\n  labelloc=t;\n"),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn keywords_are_quoted_as_identifiers() {
+    let out = render_dot(
+        "%% Diagram: T\ndirection LR\nnode[\"N\"]\nedge[\"E\"]\nsubgraph graph[\"G\"]\n    node\nend\nnode --> edge\n",
+    );
+    assert!(out.contains("  \"node\" [label=\"N\"];\n"), "{}", out);
+    assert!(out.contains("  \"edge\" [label=\"E\"];\n"), "{}", out);
+    assert!(out.contains("  subgraph cluster_graph {\n"), "{}", out);
+    assert!(out.contains("    \"node\";\n"), "{}", out);
+    assert!(out.contains("  \"node\" -> \"edge\";\n"), "{}", out);
+}
+
+#[test]
+fn node_colours_follow_the_svg_theme() {
+    let src = "%% Diagram: T\ndirection LR\nask{\"Ok?\"}\nfail[\"Error handler\"]\nask --> fail\n";
+    let out = render_dot(src);
+    assert!(
+        out.contains(
+            "ask [label=\"Ok?\", shape=diamond, fillcolor=\"#fff9c4\", color=\"#f9a825\"];"
+        ),
+        "{}",
+        out
+    );
+    assert!(out.contains("fail [label=\"Error handler\"];"), "{}", out);
+
+    let (code, out, err) = run_cli_stdin(&["render", "--format", "dot", "--infer-shapes"], src);
+    assert_eq!(code, 0, "{}", err);
+    assert!(
+        out.contains("fail [label=\"Error handler\", fillcolor=\"#ffebee\", color=\"#c62828\"];"),
+        "{}",
+        out
     );
 }
@@ -0,0 +1,163 @@
+use graphrite_core::ast::Direction;
+use graphrite_core::error::Diagnostic;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+fn analyze(src: &str) -> (graphrite_core::ast::Document, Vec<Diagnostic>) {
+    Parser::analyze(src, &ParseOptions::default(), &Registry::empty())
+}
+
+fn body(stmts: &str) -> (graphrite_core::ast::Document, Vec<Diagnostic>) {
+    analyze(&format!("%% Diagram: T\ndirection LR\n{}", stmts))
+}
+
+fn messages(diags: &[Diagnostic]) -> Vec<String> {
+    diags
+        .iter()
+        .map(|d| format!("{} {}", d.code, d.message))
+        .collect()
+}
+
+#[test]
+fn header_reads_title_and_meta() {
+    let (doc, diags) = analyze("\n%% Diagram: T\n%% other\n%% Meta: k=v\n\ndirection TD\n");
+    assert!(diags.is_empty(), "{:?}", diags);
+    let meta = doc.metadata.unwrap();
+    assert_eq!(meta.title.as_deref(), Some("T"));
+    assert_eq!(meta.tags.unwrap()["k"], "v");
+}
+
+#[test]
+fn directive_accepts_lr_and_td() {
+    let (doc, diags) = analyze("%% Diagram: T\ndirection TD\n");
+    assert!(diags.is_empty());
+    assert_eq!(doc.directives.direction, Direction::TD);
+}
+
+#[test]
+fn directive_accepts_rl_bt_and_tb() {
+    for (word, dir) in [
+        ("RL", Direction::RL),
+        ("BT", Direction::BT),
+        ("TB", Direction::TD),
+    ] {
+        let (doc, diags) = analyze(&format!("%% Diagram: T\ndirection {}\n", word));
+        assert!(diags.is_empty(), "{}: {:?}", word, diags);
+        assert_eq!(doc.directives.direction, dir);
+    }
+    let (_, diags) = analyze("%% Diagram: T\ndirection BR\n");
+    assert_eq!(diags[0].code, "E0001");
+}
+
+#[test]
+fn directive_reports_what_was_found() {
+    let (_, diags) = analyze("%% Diagram: T\ndirection \"LR\"\n");
+    assert_eq!(
+        messages(&diags),
+        ["E0001 Invalid direction: expected LR, RL, TD, TB or BT, found a string"]
+    );
+    let (_, diags) = analyze("%% Diagram: T\ndirection LR a\n");
+    assert_eq!(
+        messages(&diags),
+        [
+            "E0311 Only one statement is allowed per line",
+            "E0011 Expected '[' or an arrow after 'a', found end of line"
+        ]
+    );
+}
+
+#[test]
+fn node_decl() {
+    let (doc, diags) = body("a[\"A\"]\n");
+    assert!(diags.is_empty());
+    assert_eq!(
+        (doc.nodes[0].id.as_str(), doc.nodes[0].label.as_str()),
+        ("a", "A")
+    );
+    let (doc, diags) = body("a[\"A\" b]\n");
+    assert_eq!(
+        messages(&diags),
+        ["E0010 Expected ] after label, found identifier 'b'"]
+    );
+    assert_eq!(doc.nodes.len(), 1);
+}
+
+#[test]
+fn node_decl_label_continues_over_lines() {
+    let src = "a[\"Alpha \" \"beta \"\n    \"gamma\"]\n";
+    let (doc, diags) = body(src);
+    assert!(diags.is_empty(), "{:?}", diags);
+    assert_eq!(doc.nodes[0].label, "Alpha beta gamma");
+    let span = doc.nodes[0].label_span.as_ref().unwrap();
+    assert_eq!((span.start.line, span.end.line), (3, 4));
+    let (_, diags) = body("a[\"A\"\n\n    \"B\"]\n");
+    assert_eq!(
+        messages(&diags)[0],
+        "E0010 Expected ] after label, found end of line"
+    );
+}
+
+#[test]
+fn node_decl_trailing_tokens() {
+    let (_, diags) = body("a[\"A\"] ]\n");
+    assert_eq!(messages(&diags), ["E0011 Expected end of line, found ']'"]);
+}
+
+#[test]
+fn edge_with_and_without_label() {
+    let (doc, diags) = body("a --> b\na -.->|x| b\na -->|\"q\"| b\n");
+    assert!(diags.is_empty(), "{:?}", diags);
+    assert_eq!(doc.edges.len(), 3);
+    assert_eq!(doc.edges[1].label.as_deref(), Some("x"));
+    assert_eq!(doc.edges[2].label.as_deref(), Some("q"));
+}
+
+#[test]
+fn edge_missing_destination() {
+    let (doc, diags) = body("a -->\na --> \"b\"\n");
+    assert_eq!(
+        messages(&diags),
+        [
+            "E0011 Expected a node id after '-->', found end of line",
+            "E0011 Expected a node id after '-->', found a string"
+        ]
+    );
+    assert!(doc.edges.is_empty());
+}
+
+#[test]
+fn edge_unclosed_label() {
+    let (doc, diags) = body("a -->|x b\nc --> d\n");
+    assert_eq!(
+        messages(&diags),
+        ["E0011 Expected '|' after edge label, found identifier 'b'"]
+    );
+    assert_eq!(doc.edges.len(), 1);
+    assert_eq!(doc.edges[0].from, "c");
+}
+
+#[test]
+fn stray_tokens_are_reported_and_synchronized() {
+    let (doc, diags) = body("] a --> b\n\"x\"\nb --> a\n");
+    assert_eq!(
+        messages(&diags),
+        [
+            "E0011 Expected a node declaration or edge, found ']'",
+            "E0011 Expected a node declaration or edge, found a string"
+        ]
+    );
+    assert_eq!(doc.edges.len(), 1);
+}
+
+#[test]
+fn comment_and_blank_statements() {
+    let (_, diags) = body("\n%% note\n\n   \n");
+    assert!(diags.is_empty());
+}
+
+#[test]
+fn lexer_errors_are_not_reported_twice() {
+    let (_, diags) = body("a -> b\n");
+    let codes: Vec<&str> = diags.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, ["E0030"]);
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::{codes, diagram, run_cli};
+use graphrite_core::ast::Group;
+use graphrite_core::error::Diagnostic;
+
+fn analyze(body: &str) -> (graphrite_core::ast::Document, Vec<Diagnostic>) {
+    common::analyze(&diagram(body))
+}
+
+fn members(g: &Group) -> Vec<&str> {
+    g.nodes.iter().map(|r| r.id.as_str()).collect()
+}
+
+#[test]
+fn nested_groups_form_a_tree() {
+    let (doc, diags) = analyze(
+        "c[\"C\"]\nsubgraph outer[\"Outer\"]\n  a[\"A\"]\n  subgraph inner[\"Inner\"]\n    b[\"B\"]\n    a --> b\n  end\n  c\nend\nb --> c\n",
+    );
+    assert!(diags.is_empty(), "{:?}", diags);
+    assert_eq!(doc.nodes.len(), 3);
+    assert_eq!(doc.edges.len(), 2);
+    let outer = &doc.groups[0];
+    assert_eq!(
+        (outer.id.as_str(), outer.label.as_str()),
+        ("outer", "Outer")
+    );
+    assert_eq!(members(outer), ["a", "c"]);
+    assert_eq!(members(&outer.groups[0]), ["b"]);
+    let span = outer.groups[0].span.as_ref().unwrap();
+    assert_eq!((span.start.line, span.start.col), (6, 3));
+}
+
+#[test]
+fn end_is_still_a_node_id_outside_its_own_line() {
+    let (doc, diags) = analyze("start[\"S\"]\nend[\"E\"]\nstart --> end\n");
+    assert!(diags.is_empty(), "{:?}", diags);
+    assert_eq!(doc.nodes[1].id, "end");
+}
+
+#[test]
+fn unbalanced_blocks_are_reported() {
+    let (doc, diags) = analyze("subgraph g[\"G\"]\n  a[\"A\"]\n  a --> a\n");
+    assert_eq!(codes(&diags), ["E0012"]);
+    assert_eq!(diags[0].message, "Subgraph 'g' is missing 'end'");
+    assert_eq!(members(&doc.groups[0]), ["a"]);
+
+    let (_, diags) = analyze("a[\"A\"]\na --> a\nend\n");
+    assert_eq!(codes(&diags), ["E0011"]);
+    assert_eq!(diags[0].message, "Found 'end' with no open subgraph");
+}
+
+#[test]
+fn malformed_header_still_matches_its_end() {
+    let (doc, diags) = analyze("subgraph g\n  a[\"A\"]\nend\na --> a\n");
+    assert_eq!(codes(&diags), ["E0011"]);
+    assert!(doc.groups.is_empty());
+}
+
+#[test]
+fn group_ids_are_snake_case() {
+    let (_, diags) = analyze("subgraph WebTier[\"Web\"]\n  a[\"A\"]\nend\na --> a\n");
+    assert_eq!(codes(&diags), ["E0100"]);
+    assert!(!diags[0].fixes.is_empty());
+}
+
+#[test]
+fn membership_is_validated() {
+    let (_, diags) = analyze(
+        "a[\"A\"]\nb[\"B\"]\nsubgraph one[\"One\"]\n  a\n  ghost\nend\nsubgraph two[\"Two\"]\n  a\nend\na --> b\n",
+    );
+    assert_eq!(codes(&diags), ["E0205", "E0206"]);
+    assert_eq!(
+        diags[0].message,
+        "Subgraph 'one' lists unknown node 'ghost'"
+    );
+    assert_eq!(diags[1].message, "Node 'a' is already in subgraph 'one'");
+    assert_eq!(diags[1].span.as_ref().unwrap().start.line, 10);
+    assert_eq!(diags[1].related[0].span.start.line, 6);
+}
+
+#[test]
+fn group_ids_do_not_clash() {
+    let (_, diags) = analyze(
+        "a[\"A\"]\nsubgraph a[\"G\"]\nend\nsubgraph g[\"G\"]\nend\nsubgraph g[\"G\"]\nend\na --> a\n",
+    );
+    assert_eq!(codes(&diags), ["E0207", "E0207"]);
+    assert_eq!(diags[0].related[0].message, "node declared here");
+    assert_eq!(diags[1].related[0].message, "subgraph declared here");
+}
+
+#[test]
+fn dot_renders_clusters() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../samples/valid/service_tiers.mmd");
+    let (code, out, err) = run_cli(&["render", "--format", "dot", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", err);
+    let style = "style=\"rounded,filled\"; fillcolor=\"#f5f7fa\"; color=\"#90a4ae\"; fontcolor=\"#455a64\";";
+    let app = format!("  subgraph cluster_app_tier {{\n    label=\"Application\";\n    {style}\n    web;\n    api;\n    subgraph cluster_workers {{\n      label=\"Background Workers\";\n      {style}\n      mailer;\n      thumbnailer;\n    }}\n  }}\n");
+    assert!(out.contains(&app), "{}", out);
+    // clusters come after every node declaration and before the edges
+    let first_cluster = out.find("subgraph").unwrap();
+    assert!(out.find("blobs [label").unwrap() < first_cluster);
+    assert!(out.find("users -> cdn").unwrap() > first_cluster);
+}
+
+#[test]
+fn svg_draws_group_boxes_around_members() {
+    let path = std::env::temp_dir().join("graphrite_groups.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection TD\na[\"A\"]\nsubgraph g[\"Group & Co\"]\n  b[\"B\"]\n  c[\"C\"]\nend\na --> b\na --> c\n",
+    )
+    .unwrap();
+    let (code, out, err) = run_cli(&["render", "--format", "svg", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", err);
+    assert!(
+        out.contains("<g class=\"group\" id=\"group-g\">"),
+        "{}",
+        out
+    );
+    assert!(out.contains(">Group &amp; Co</text>"), "{}", out);
+    // the box is drawn before, and so beneath, the nodes
+    assert!(out.find("group-g").unwrap() < out.find(">B</text>").unwrap());
+    let num = |s: &str, attr: &str| -> f32 {
+        let at = s.find(&format!(" {}=\"", attr)).unwrap() + attr.len() + 3;
+        s[at..].split('"').next().unwrap().parse().unwrap()
+    };
+    let group = &out[out.find("group-g").unwrap()..];
+    let (gx, gy, gw, gh) = (
+        num(group, "x"),
+        num(group, "y"),
+        num(group, "width"),
+        num(group, "height"),
+    );
+    for label in [">B</text>", ">C</text>"] {
+        let text = &out[..out.find(label).unwrap()];
+        let rect = &text[text.rfind("<rect").unwrap()..];
+        let (x, y) = (num(rect, "x"), num(rect, "y"));
+        assert!(gx < x && x + 160.0 < gx + gw, "{} outside box", label);
+        assert!(gy < y && y + 48.0 < gy + gh, "{} outside box", label);
+    }
+}
+
+#[test]
+fn svg_group_box_spans_nodes_between_its_members() {
+    let path = std::env::temp_dir().join("graphrite_groups_between.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\nsubgraph g[\"G\"]\n  a[\"A\"]\n  c[\"C\"]\nend\nb[\"B\"]\na --> b\nb --> c\n",
+    )
+    .unwrap();
+    let (code, out, err) = run_cli(&["render", "--format", "svg", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", err);
+    let num = |s: &str, attr: &str| -> f32 {
+        let at = s.find(&format!(" {}=\"", attr)).unwrap() + attr.len() + 3;
+        s[at..].split('"').next().unwrap().parse().unwrap()
+    };
+    let group = &out[out.find("group-g").unwrap()..];
+    let (gx, gw) = (num(group, "x"), num(group, "width"));
+    let x = |label: &str| {
+        let text = &out[..out.find(label).unwrap()];
+        num(&text[text.rfind("<rect").unwrap()..], "x")
+    };
+    // the box is the bounding box of a and c, one layer apart, so it covers b too
+    assert!(
+        gx < x(">A</text>") && x(">C</text>") + 160.0 < gx + gw,
+        "{}",
+        out
+    );
+    assert!(
+        gx < x(">B</text>") && x(">B</text>") + 160.0 < gx + gw,
+        "{}",
+        out
+    );
+}
@@ -2,7 +2,7 @@ use graphrite_core::parser::Parser;
 
 #[test]
 fn accepts_valid_snake_case_ids() {
-    let src = "direction LR\nfoo_1[\"F\"]\nbar2[\"B\"]\nfoo_1 --> bar2\n";
+    let src = "%% Diagram: T\ndirection LR\nfoo_1[\"F\"]\nbar2[\"B\"]\nfoo_1 --> bar2\n";
     let doc = Parser::parse(src).unwrap();
     assert_eq!(doc.nodes.len(), 2);
 }
@@ -0,0 +1,101 @@
+mod common;
+
+use graphrite_core::fix::apply_fixes;
+use graphrite_core::lexer::Lexer;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+fn diags(body: &str) -> Vec<graphrite_core::error::Diagnostic> {
+    common::analyze(&common::diagram(body)).1
+}
+
+#[test]
+fn single_arrow_is_reported_with_fix() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -> b\n";
+    let (_, ds) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    let d = ds.iter().find(|d| d.code == "E0030").unwrap();
+    assert!(d.message.contains("'->'"));
+    let span = d.span.as_ref().unwrap();
+    assert_eq!(&src[span.start.offset..span.end.offset], "->");
+    let (fixed, n) = apply_fixes(src, &ds);
+    assert_eq!(n, 1);
+    assert!(Parser::parse(&fixed).is_ok(), "{}", fixed);
+}
+
+#[test]
+fn single_arrow_still_connects_its_nodes() {
+    let codes: Vec<String> = diags("a[\"A\"]\nb[\"B\"]\na -> b\n")
+        .into_iter()
+        .map(|d| d.code)
+        .collect();
+    assert_eq!(codes, ["E0030"]);
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -> b\n";
+    let (doc, _) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    assert_eq!(
+        (doc.edges[0].from.as_str(), doc.edges[0].to.as_str()),
+        ("a", "b")
+    );
+}
+
+#[test]
+fn unknown_characters_and_leading_digits_are_reported() {
+    let ds = diags("a;\n1abc[\"X\"]\n");
+    let msgs: Vec<&str> = ds
+        .iter()
+        .filter(|d| d.code == "E0030")
+        .map(|d| d.message.as_str())
+        .collect();
+    assert_eq!(
+        msgs,
+        [
+            "Unexpected character ';'",
+            "Unexpected '1abc'; identifiers start with a letter"
+        ]
+    );
+}
+
+#[test]
+fn parentheses_alone_are_not_a_shape() {
+    let ds = diags("a(\"Label\")\n");
+    assert!(ds.iter().all(|d| d.code != "E0030"), "{:?}", ds);
+    let d = ds.iter().find(|d| d.code == "E0011").unwrap();
+    assert_eq!(d.message, "Expected '[', found '('");
+    // reported at the bracket that fails to open a shape, not at the label
+    assert_eq!(d.span.as_ref().unwrap().start.col, 2);
+}
+
+#[test]
+fn unterminated_string_spans_to_end_of_line() {
+    let (_, ds) = Lexer::lex("a[\"Open label]\nb\n");
+    assert_eq!(ds.len(), 1);
+    assert_eq!(ds[0].code, "E0031");
+    let span = ds[0].span.as_ref().unwrap();
+    assert_eq!((span.start.col, span.end.col), (3, 15));
+    assert_eq!(span.end.line, 1);
+}
+
+#[test]
+fn invalid_escape_is_reported() {
+    let (toks, ds) = Lexer::lex(r#"a["tab\there"]"#);
+    assert_eq!(ds.len(), 1);
+    assert_eq!(ds[0].code, "E0032");
+    assert_eq!(ds[0].message, "Invalid escape '\\t' in string");
+    let span = ds[0].span.as_ref().unwrap();
+    assert_eq!((span.start.col, span.end.col), (7, 9));
+    assert!(
+        matches!(&toks[2].kind, graphrite_core::lexer::TokenKind::StringLit(s) if s == "tab\\there")
+    );
+}
+
+#[test]
+fn unquoted_label_is_not_reported_per_character() {
+    let ds = diags("a[Step 1: go!]\na --> a\n");
+    let codes: Vec<&str> = ds.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, ["E0003"]);
+}
+
+#[test]
+fn valid_input_has_no_lexer_diagnostics() {
+    let (_, ds) = Lexer::lex("%% note\na[\"Ünïcode → ok\"]\na -.->|x| b\n");
+    assert!(ds.is_empty(), "{:?}", ds);
+}
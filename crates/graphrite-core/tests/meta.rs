@@ -0,0 +1,46 @@
+use graphrite_core::parser::Parser;
+
+#[test]
+fn parses_meta_tags() {
+    let src = "%% Diagram: T\n%% Meta: type=architecture; domain=social\n%% Meta: version=1;\n\ndirection LR\na[\"A\"]\na --> a\n";
+    let doc = Parser::parse(src).unwrap();
+    let meta = doc.metadata.unwrap();
+    assert_eq!(meta.title.as_deref(), Some("T"));
+    let tags = meta.tags.unwrap();
+    assert_eq!(tags.len(), 3);
+    assert_eq!(tags["type"], "architecture");
+    assert_eq!(tags["domain"], "social");
+    assert_eq!(tags["version"], "1");
+}
+
+#[test]
+fn bluesky_meta_tags() {
+    let src = include_str!("../../../samples/valid/bluesky.mmd");
+    let doc = Parser::parse(src).unwrap();
+    let tags = doc.metadata.unwrap().tags.unwrap();
+    assert_eq!(tags["type"], "architecture");
+}
+
+#[test]
+fn malformed_meta_entry() {
+    let src = "%% Meta: type=workflow; oops; =x\ndirection LR\na[\"A\"]\na --> a\n";
+    let errs = Parser::parse(src).unwrap_err();
+    let bad: Vec<_> = errs.iter().filter(|d| d.code == "E0020").collect();
+    assert_eq!(bad.len(), 2);
+    let span = bad[0].span.as_ref().unwrap();
+    assert_eq!((span.start.line, span.start.col), (1, 25));
+}
+
+#[test]
+fn duplicate_meta_key() {
+    let src = "%% Meta: type=a\n%% Meta: type=b\ndirection LR\na[\"A\"]\na --> a\n";
+    let errs = Parser::parse(src).unwrap_err();
+    assert!(errs.iter().any(|d| d.code == "E0021"));
+}
+
+#[test]
+fn meta_outside_header() {
+    let src = "direction LR\n%% Meta: type=a\na[\"A\"]\na --> a\n";
+    let errs = Parser::parse(src).unwrap_err();
+    assert!(errs.iter().any(|d| d.code == "E0022"));
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::run_cli;
+use std::process::Command;
+
+fn invalid(name: &str) -> String {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../samples/invalid")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn check_renders_snippet_with_underline() {
+    let path = invalid("02-id-not-snake.mmd");
+    let (code, _, err) = run_cli(&["check", "--color", "never", &path]);
+    assert_eq!(code, 1);
+    assert!(err.contains("error[E0100]: Identifier must be snake_case"));
+    assert!(err.contains(&format!(" --> {}:5:1", path)));
+    assert!(err.contains("5 | Foo[\"A\"]\n  | ^^^\n"));
+    assert!(err.contains("= help: Identifiers must match"));
+    assert!(err.contains("= suggestion: rename 'Foo' to 'foo'"));
+    assert!(!err.contains('\x1b'));
+}
+
+#[test]
+fn color_always_emits_ansi() {
+    let path = invalid("02-id-not-snake.mmd");
+    let (_, _, err) = run_cli(&["check", "--color=always", &path]);
+    assert!(err.contains("\x1b[1;31merror[E0100]"));
+}
+
+#[test]
+fn stdin_is_named_in_reports() {
+    let output = Command::new("cargo")
+        .args(["run", "-q", "-p", "graphrite-cli", "--", "lint", "--pretty"])
+        .arg("--color=never")
+        .stdin(std::fs::File::open(invalid("05-dangling-edge.mmd")).unwrap())
+        .output()
+        .expect("run cli");
+    let err = String::from_utf8_lossy(&output.stderr);
+    assert!(err.contains(" --> <stdin>:5:1"));
+    assert!(err.contains("error[E0201]"));
+}
+
+#[test]
+fn related_locations_are_rendered() {
+    let path = std::env::temp_dir().join("graphrite_report_dup.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\na[\"One\"]\nb[\"B\"]\na[\"Two\"]\na --> b\n",
+    )
+    .unwrap();
+    let (code, _, err) = run_cli(&["check", "--color=never", path.to_str().unwrap()]);
+    assert_eq!(code, 1);
+    assert!(err.contains("error[E0204]: Node 'a' is declared more than once"));
+    assert!(err.contains("5 | a[\"Two\"]\n  | ^^^^^^^^\n"));
+    assert!(err.contains("  ::: "));
+    assert!(err.contains("3 | a[\"One\"]\n  | -------- first declared here\n"));
+}
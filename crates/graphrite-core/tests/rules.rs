@@ -0,0 +1,67 @@
+use graphrite_core::ast::Document;
+use graphrite_core::error::Diagnostic;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::{Registry, Rule};
+
+struct DecisionHasTwoExits;
+
+impl Rule for DecisionHasTwoExits {
+    fn id(&self) -> &'static str {
+        "X0001"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<Diagnostic> {
+        doc.nodes
+            .iter()
+            .filter(|n| n.label.ends_with('?'))
+            .filter(|n| doc.edges.iter().filter(|e| e.from == n.id).count() != 2)
+            .map(|n| {
+                self.diagnostic(
+                    format!("Decision '{}' must have two outgoing edges", n.id),
+                    n.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+const SRC: &str = "%% Diagram: T\ndirection LR\nask[\"Ok?\"]\nyes[\"Yes\"]\nask --> yes\n";
+
+#[test]
+fn default_registry_has_builtin_rules() {
+    let reg = Registry::default();
+    let ids: Vec<&str> = reg.rules().map(|r| r.id()).collect();
+    assert_eq!(
+        ids,
+        ["E0201", "E0202", "E0203", "E0204", "E0205", "E0206", "E0207", "W0301"]
+    );
+}
+
+#[test]
+fn custom_rule_runs_alongside_builtins() {
+    let mut reg = Registry::default();
+    reg.register(DecisionHasTwoExits);
+    let errs = Parser::parse_with_rules(SRC, &ParseOptions::default(), &reg).unwrap_err();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].code, "X0001");
+    assert_eq!(errs[0].name, None);
+    assert!(Parser::parse(SRC).is_ok());
+}
+
+#[test]
+fn empty_registry_skips_semantic_checks() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb --> c\n";
+    assert!(Parser::parse(src).is_err());
+    let doc = Parser::parse_with_rules(src, &ParseOptions::default(), &Registry::empty()).unwrap();
+    assert_eq!(doc.edges.len(), 1);
+}
+
+#[test]
+fn builtin_rules_name_their_diagnostics() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na --> a\n";
+    let doc = Parser::parse_with_rules(src, &ParseOptions::default(), &Registry::empty()).unwrap();
+    let diags = Registry::default().check(&doc);
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].name.as_deref(), Some("OrphanNode"));
+    assert!(diags[0].is_error());
+}
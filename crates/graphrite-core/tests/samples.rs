@@ -1,22 +1,10 @@
-use std::process::Command;
+mod common;
 
-fn run_cli(args: &[&str]) -> (i32, String, String) {
-    let output = Command::new("cargo")
-        .args(["run", "-q", "-p", "graphrite-cli", "--"])
-        .args(args)
-        .output()
-        .expect("run cli");
-    (
-        output.status.code().unwrap_or(1),
-        String::from_utf8_lossy(&output.stdout).into_owned(),
-        String::from_utf8_lossy(&output.stderr).into_owned(),
-    )
-}
+use common::run_cli;
 
 #[test]
 fn validate_all_valid_samples() {
-    let samples_dir =
-        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../samples/valid");
+    let samples_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../samples/valid");
     for entry in std::fs::read_dir(&samples_dir).unwrap() {
         let p = entry.unwrap().path();
         if p.extension().and_then(|s| s.to_str()) != Some("mmd") {
@@ -29,7 +29,9 @@ fn errors_without_direction() {
 
 #[test]
 fn edges_validate_presence() {
-    let src = r#"direction LR
+    let src = r#"%% Diagram: Forward reference
+
+direction LR
 
 a["A"]
 a --> b
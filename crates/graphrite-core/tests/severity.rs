@@ -0,0 +1,69 @@
+mod common;
+
+use common::run_cli;
+use graphrite_core::error::Severity;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+fn long_label_src() -> String {
+    format!(
+        "%% Diagram: T\ndirection LR\na[\"{}\"]\nb[\"B\"]\na --> b\n",
+        "x".repeat(60)
+    )
+}
+
+fn write_tmp(name: &str, src: &str) -> String {
+    let p = std::env::temp_dir().join(name);
+    std::fs::write(&p, src).unwrap();
+    p.to_string_lossy().into_owned()
+}
+
+#[test]
+fn warnings_do_not_fail_parse() {
+    let src = long_label_src();
+    assert!(Parser::parse(&src).is_ok());
+    let (_, diags) = Parser::analyze(&src, &ParseOptions::default(), &Registry::default());
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, "W0301");
+    assert_eq!(diags[0].severity, Severity::Warning);
+}
+
+#[test]
+fn severity_override_promotes_warning() {
+    let src = long_label_src();
+    let mut reg = Registry::default();
+    reg.set_severity("W0301", Severity::Error);
+    let errs = Parser::parse_with_rules(&src, &ParseOptions::default(), &reg).unwrap_err();
+    assert!(errs[0].is_error());
+}
+
+#[test]
+fn errors_default_to_error_severity() {
+    let errs = Parser::parse("direction LR\nFoo[\"F\"]\n").unwrap_err();
+    assert!(errs.iter().all(|d| d.severity == Severity::Error));
+}
+
+#[test]
+fn check_passes_with_warnings_unless_denied() {
+    let path = write_tmp("graphrite_severity_check.mmd", &long_label_src());
+    let (code, out, err) = run_cli(&["check", &path]);
+    assert_eq!(code, 0, "stderr: {}", err);
+    assert!(out.contains("ok"));
+    assert!(err.contains("W0301"));
+    let (code, _, _) = run_cli(&["check", "--deny-warnings", &path]);
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn lint_reports_warnings() {
+    let path = write_tmp("graphrite_severity_lint.mmd", &long_label_src());
+    let (code, out, _) = run_cli(&["lint", &path]);
+    assert_eq!(code, 0);
+    // the plain output stays `code<TAB>message`; severity shows in the report and JSON
+    assert_eq!(out, "W0301\tLabel of 'a' is 60 characters (> 50)\n");
+    let (_, _, err) = run_cli(&["lint", "--pretty", &path]);
+    assert!(err.contains("warning[W0301]"), "{}", err);
+    let (code, out, _) = run_cli(&["lint", "--diag-json", "--deny-warnings", &path]);
+    assert_eq!(code, 1);
+    assert!(out.contains("\"severity\": \"warning\""));
+}
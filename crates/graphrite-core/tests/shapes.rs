@@ -0,0 +1,163 @@
+mod common;
+
+use common::run_cli;
+use graphrite_core::ast::Shape;
+use graphrite_core::fix::apply_fixes;
+use graphrite_core::fmt::format;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+const SHAPES: &str = "%% Diagram: T\ndirection LR\nstart([\"Start\"])\nask{\"Ready?\"}\nstore[(\"Store\")]\nstep[\"Data Scientist Review\"]\nstart --> ask\nask --> store\nask --> step\n";
+
+#[test]
+fn brackets_choose_the_shape() {
+    let doc = Parser::parse(SHAPES).unwrap();
+    let shapes: Vec<(&str, Shape)> = doc
+        .nodes
+        .iter()
+        .map(|n| (n.label.as_str(), n.shape))
+        .collect();
+    assert_eq!(
+        shapes,
+        [
+            ("Start", Shape::Stadium),
+            ("Ready?", Shape::Diamond),
+            ("Store", Shape::Cylinder),
+            ("Data Scientist Review", Shape::Rect),
+        ]
+    );
+    // the label span covers the string only
+    let span = doc.nodes[2].label_span.as_ref().unwrap();
+    assert_eq!(&SHAPES[span.start.offset..span.end.offset], "\"Store\"");
+}
+
+#[test]
+fn json_names_shapes_other_than_rect() {
+    let doc = Parser::parse(SHAPES).unwrap();
+    let json = serde_json::to_value(&doc).unwrap();
+    assert_eq!(json["nodes"][1]["shape"], "diamond");
+    assert!(json["nodes"][3].get("shape").is_none());
+}
+
+#[test]
+fn closing_brackets_must_match() {
+    let src = "%% Diagram: T\ndirection LR\na{\"A\"]\na --> a\n";
+    let (_, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    assert_eq!(diags[0].code, "E0010");
+    assert_eq!(diags[0].message, "Expected } after label, found ']'");
+
+    let src = "%% Diagram: T\ndirection LR\na[(\"A\"]\na --> a\n";
+    let (_, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    assert_eq!(diags[0].message, "Expected )] after label, found ']'");
+}
+
+#[test]
+fn fmt_keeps_and_wraps_shapes() {
+    assert_eq!(
+        format(SHAPES).unwrap().lines().nth(4),
+        Some("start([\"Start\"])")
+    );
+    let label = "Word ".repeat(30);
+    let src = format!(
+        "%% Diagram: T\ndirection LR\nstore[(\"{}\")]\nstore --> store\n",
+        label.trim_end()
+    );
+    let out = format(&src).unwrap();
+    assert!(out.lines().all(|l| l.chars().count() <= 100), "{}", out);
+    assert!(out.contains("store[(\"Word"), "{}", out);
+    assert!(out.contains("Word\")]\n"), "{}", out);
+    let doc = Parser::parse(&out).unwrap();
+    assert_eq!(doc.nodes[0].shape, Shape::Cylinder);
+    assert_eq!(doc.nodes[0].label, label.trim_end());
+}
+
+#[test]
+fn long_line_fix_keeps_the_shape() {
+    let src = format!(
+        "%% Diagram: T\ndirection LR\nask{{\"{}?\"}}\nask --> ask\n",
+        "Really ".repeat(16).trim_end()
+    );
+    let (_, diags) = Parser::analyze(&src, &ParseOptions::default(), &Registry::default());
+    let (fixed, n) = apply_fixes(&src, &diags);
+    assert_eq!(n, 1);
+    let doc = Parser::parse(&fixed).unwrap();
+    assert_eq!(doc.nodes[0].shape, Shape::Diamond);
+}
+
+#[test]
+fn dot_carries_shapes() {
+    let path = std::env::temp_dir().join("graphrite_shapes.mmd");
+    std::fs::write(&path, SHAPES).unwrap();
+    let (code, out, err) = run_cli(&["render", "--format", "dot", path.to_str().unwrap()]);
+    assert_eq!(code, 0, "{}", err);
+    assert!(out.contains("  node [shape=box, style=filled, "), "{}", out);
+    assert!(out.contains("start [label=\"Start\", style=\"rounded,filled\", "));
+    assert!(out.contains("ask [label=\"Ready?\", shape=diamond, "));
+    assert!(out.contains("store [label=\"Store\", shape=cylinder, "));
+    assert!(out.contains("step [label=\"Data Scientist Review\"];"));
+}
+
+#[test]
+fn svg_guesses_shapes_only_when_asked() {
+    let path = std::env::temp_dir().join("graphrite_shapes_infer.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\nstep[\"Data Scientist Review\"]\nask{\"Ready?\"}\nstep --> ask\n",
+    )
+    .unwrap();
+    let p = path.to_str().unwrap();
+    let (code, plain, err) = run_cli(&["render", "--format", "svg", p]);
+    assert_eq!(code, 0, "{}", err);
+    // one polygon for the diamond node, one for its legend entry
+    assert_eq!(plain.matches("<polygon").count(), 2, "{}", plain);
+    assert!(!plain.contains(">Error</text>"), "{}", plain);
+
+    let (code, inferred, err) = run_cli(&["render", "--format", "svg", "--infer-shapes", p]);
+    assert_eq!(code, 0, "{}", err);
+    assert!(inferred.contains(">Error</text>"), "{}", inferred);
+    // "Data" makes the plain rectangle a cylinder
+    assert!(
+        inferred.matches("<ellipse").count() > plain.matches("<ellipse").count(),
+        "{}",
+        inferred
+    );
+}
+
+#[test]
+fn end_terminals_are_grey_only_when_inferring() {
+    let path = std::env::temp_dir().join("graphrite_shapes_terminals.mmd");
+    std::fs::write(
+        &path,
+        "%% Diagram: T\ndirection LR\nstart([\"Go\"])\nend_ok([\"Done\"])\nstart --> end_ok\n",
+    )
+    .unwrap();
+    let p = path.to_str().unwrap();
+    let green = "style=\"rounded,filled\", fillcolor=\"#e6ffe6\", color=\"#2e7d32\"];";
+    let grey = "style=\"rounded,filled\", fillcolor=\"#eeeeee\", color=\"#424242\"];";
+
+    let (code, out, err) = run_cli(&["render", "--format", "dot", p]);
+    assert_eq!(code, 0, "{}", err);
+    assert!(
+        out.contains(&format!("start [label=\"Go\", {}", green)),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains(&format!("end_ok [label=\"Done\", {}", green)),
+        "{}",
+        out
+    );
+
+    let (code, out, err) = run_cli(&["render", "--format", "dot", "--infer-shapes", p]);
+    assert_eq!(code, 0, "{}", err);
+    assert!(
+        out.contains(&format!("start [label=\"Go\", {}", green)),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains(&format!("end_ok [label=\"Done\", {}", grey)),
+        "{}",
+        out
+    );
+}
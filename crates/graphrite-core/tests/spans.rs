@@ -0,0 +1,55 @@
+use graphrite_core::ast::Span;
+use graphrite_core::parser::{ParseOptions, Parser};
+use graphrite_core::validate::Registry;
+
+fn text<'a>(src: &'a str, span: &Span) -> &'a str {
+    &src[span.start.offset..span.end.offset]
+}
+
+#[test]
+fn node_and_label_spans_are_exclusive() {
+    let src = "%% Diagram: T\ndirection LR\nalpha[\"Alpha\"]\nalpha --> alpha\n";
+    let doc = Parser::parse(src).unwrap();
+    let node = &doc.nodes[0];
+    let span = node.span.as_ref().unwrap();
+    assert_eq!(text(src, span), "alpha[\"Alpha\"]");
+    assert_eq!((span.start.line, span.start.col), (3, 1));
+    assert_eq!((span.end.line, span.end.col), (3, 15));
+    assert_eq!(text(src, node.label_span.as_ref().unwrap()), "\"Alpha\"");
+}
+
+#[test]
+fn edge_and_label_spans_cover_source() {
+    let src = "%% Diagram: T\ndirection LR\na[\"A\"]\nb[\"B\"]\na -.->|maybe| b\n";
+    let doc = Parser::parse(src).unwrap();
+    let edge = &doc.edges[0];
+    assert_eq!(text(src, edge.span.as_ref().unwrap()), "a -.->|maybe| b");
+    assert_eq!(text(src, edge.label_span.as_ref().unwrap()), "maybe");
+    assert_eq!(edge.span.as_ref().unwrap().end.col, 16);
+}
+
+#[test]
+fn diagnostic_spans_cover_offending_text() {
+    let src = "%% Diagram: T\ndirection LR\nFooBar[\"A\"]\nx[Hello World]\nFooBar --> x\n";
+    let (_, diags) = Parser::analyze(src, &ParseOptions::default(), &Registry::default());
+    let span = |code: &str| {
+        diags
+            .iter()
+            .find(|d| d.code == code)
+            .and_then(|d| d.span.clone())
+            .unwrap()
+    };
+    assert_eq!(text(src, &span("E0100")), "FooBar");
+    assert_eq!(text(src, &span("E0003")), "Hello World");
+}
+
+#[test]
+fn long_line_span_is_whole_line() {
+    let long = format!("a[\"{}\"]", "x".repeat(120));
+    let src = format!("%% Diagram: T\ndirection LR\n{}\na --> a\n", long);
+    let errs = Parser::parse(&src).unwrap_err();
+    let d = errs.iter().find(|d| d.code == "E0300").unwrap();
+    let span = d.span.as_ref().unwrap();
+    assert_eq!(text(&src, span), long);
+    assert_eq!(span.end.col, long.len() + 1);
+}
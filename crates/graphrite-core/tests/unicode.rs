@@ -0,0 +1,62 @@
+use graphrite_core::lexer::{Lexer, TokenKind};
+use graphrite_core::parser::Parser;
+
+fn sample() -> &'static str {
+    include_str!("../../../samples/valid/multilingual.mmd")
+}
+
+#[test]
+fn labels_round_trip_utf8() {
+    let doc = Parser::parse(sample()).unwrap();
+    let labels: Vec<&str> = doc.nodes.iter().map(|n| n.label.as_str()).collect();
+    assert_eq!(
+        labels,
+        [
+            "Café d'accueil",
+            "Commande → Cuisine",
+            "厨房サービス",
+            "Доставка 🚚",
+            "Terminé ✓"
+        ]
+    );
+    let edge_labels: Vec<_> = doc.edges.iter().filter_map(|e| e.label.as_deref()).collect();
+    assert_eq!(edge_labels, ["注文", "Ελλάδα"]);
+    let meta = doc.metadata.unwrap();
+    assert_eq!(meta.title.as_deref(), Some("Café → Commande (多言語)"));
+    assert_eq!(meta.tags.unwrap()["言語"], "日本語");
+}
+
+#[test]
+fn columns_in_bytes_and_utf16() {
+    let toks = Lexer::tokenize("x[\"é🚚\"] y\n");
+    let y = toks
+        .iter()
+        .find(|t| t.kind == TokenKind::Identifier("y".into()))
+        .unwrap();
+    // `é` is 2 bytes / 1 unit, `🚚` is 4 bytes / 2 units
+    assert_eq!(y.col, 13);
+    assert_eq!(y.col_utf16, 10);
+    let lit = &toks[2];
+    assert_eq!(lit.kind, TokenKind::StringLit("é🚚".into()));
+    assert_eq!((lit.len, lit.len_utf16), (8, 5));
+}
+
+#[test]
+fn spans_slice_source_on_char_boundaries() {
+    let src = sample();
+    let doc = Parser::parse(src).unwrap();
+    for n in &doc.nodes {
+        let span = n.label_span.as_ref().unwrap();
+        assert_eq!(
+            &src[span.start.offset..span.end.offset],
+            format!("\"{}\"", n.label)
+        );
+        assert!(span.end.col_utf16 <= span.end.col);
+    }
+}
+
+#[test]
+fn escapes_survive_next_to_multibyte_chars() {
+    let toks = Lexer::tokenize(r#"a["ü\"ß\\"]"#);
+    assert_eq!(toks[2].kind, TokenKind::StringLit("ü\"ß\\".into()));
+}
@@ -16,7 +16,7 @@ fn dangling_edge_from() {
 
 #[test]
 fn spans_present_on_nodes_and_edges() {
-    let src = "direction LR\nfoo[\"Foo\"]\nfoo --> foo\n";
+    let src = "%% Diagram: T\ndirection LR\nfoo[\"Foo\"]\nfoo --> foo\n";
     let doc = Parser::parse(src).unwrap();
     assert!(doc.nodes[0].span.is_some());
     assert!(doc.edges[0].span.is_some());
@@ -36,3 +36,16 @@ fn line_length_limit() {
     let err = Parser::parse(&src).unwrap_err();
     assert!(err.iter().any(|d| d.code == "E0300"));
 }
+
+#[test]
+fn duplicate_node_points_at_first_declaration() {
+    let src = "%% Diagram: T\ndirection LR\na[\"One\"]\nb[\"B\"]\na[\"Two\"]\na --> b\n";
+    let err = Parser::parse(src).unwrap_err();
+    let dups: Vec<_> = err.iter().filter(|d| d.code == "E0204").collect();
+    assert_eq!(dups.len(), 1);
+    assert_eq!(dups[0].span.as_ref().unwrap().start.line, 5);
+    let related = &dups[0].related;
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].message, "first declared here");
+    assert_eq!(related[0].span.start.line, 3);
+}